@@ -1,41 +1,107 @@
 use std::{
+    cmp::Reverse,
     collections::{BinaryHeap, HashMap, HashSet},
-    iter, mem,
 };
 
 use crate::types::{
-    Agenda, Handicraft, HandicraftGraphNode, HandicraftName, HandicraftPricingInfo, PopSupply,
-    RareItemCount,
+    agenda_step, find_handicraft, Agenda, Groove, GrooveAgendas, Handicraft, HandicraftGraphNode,
+    HandicraftName, HandicraftPricingInfo, MaterialBudget, MaterialGraphNode, MaterialName,
+    PopSupply, PricingModifiers, WorkshopRank,
 };
 use memoize::memoize;
-use petgraph::{prelude::GraphMap, Directed};
+use thiserror::Error;
 
-const TIME_IN_CYCLE: usize = 24;
+use petgraph::{prelude::GraphMap, Directed, Direction::Incoming};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Hours in one workshop cycle
+pub const TIME_IN_CYCLE: usize = 24;
 const MIN_PRODUCT_TIME: usize = 4;
 
-// type MaterialGraph = GraphMap<MaterialGraphNode, u8, Directed>;
-type HandicraftGraph = GraphMap<HandicraftGraphNode, u8, Directed>;
-
-// fn create_material_graph<'a, I>(handicrafts: I) -> (HashSet<HandicraftName>, MaterialGraph)
-// where
-//     I: Iterator<Item = &'a Handicraft>,
-// {
-//     let mut graph = GraphMap::new();
-//     let recipe_nodes = handicrafts
-//         .map(|item| {
-//             for mat in &item.materials {
-//                 let recipe_node = graph.add_node(MaterialGraphNode::Handicraft(item.name));
-//                 graph.add_edge(
-//                     recipe_node,
-//                     MaterialGraphNode::Material(*mat.0),
-//                     u8::default(),
-//                 );
-//             }
-//             item.name
-//         })
-//         .collect();
-//     (recipe_nodes, graph)
-// }
+pub type MaterialGraph = GraphMap<MaterialGraphNode, u8, Directed>;
+pub type HandicraftGraph = GraphMap<HandicraftGraphNode, u8, Directed>;
+
+pub fn create_material_graph<'a, I>(handicrafts: I) -> (HashSet<HandicraftName>, MaterialGraph)
+where
+    I: Iterator<Item = &'a Handicraft>,
+{
+    let mut graph = GraphMap::new();
+    let recipe_nodes = handicrafts
+        .map(|item| {
+            for mat in &item.materials {
+                let recipe_node = graph.add_node(MaterialGraphNode::Handicraft(item.name));
+                graph.add_edge(
+                    recipe_node,
+                    MaterialGraphNode::Material(*mat.0),
+                    u8::default(),
+                );
+            }
+            item.name
+        })
+        .collect();
+    (recipe_nodes, graph)
+}
+
+/// Handicrafts that use the material
+pub fn handicrafts_using(graph: &MaterialGraph, material: MaterialName) -> HashSet<HandicraftName> {
+    graph
+        .neighbors_directed(MaterialGraphNode::Material(material), Incoming)
+        .map(MaterialGraphNode::unwrap_handicraft)
+        .collect()
+}
+
+/// Materials both handicrafts use
+pub fn shared_materials(
+    graph: &MaterialGraph,
+    first: HandicraftName,
+    second: HandicraftName,
+) -> HashSet<MaterialName> {
+    let materials = |name| {
+        graph
+            .neighbors(MaterialGraphNode::Handicraft(name))
+            .map(MaterialGraphNode::unwrap_material)
+            .collect::<HashSet<_>>()
+    };
+    &materials(first) & &materials(second)
+}
+
+/// Handicrafts that can't be made once any of the materials runs out
+pub fn unmakeable_without<I>(graph: &MaterialGraph, materials: I) -> HashSet<HandicraftName>
+where
+    I: IntoIterator<Item = MaterialName>,
+{
+    materials
+        .into_iter()
+        .flat_map(|material| handicrafts_using(graph, material))
+        .collect()
+}
+
+/// Handicrafts other than the excluded ones, to build the graph from
+pub fn excluding_handicrafts<'a, I>(
+    handicrafts: I,
+    excluded: &'a HashSet<HandicraftName>,
+) -> impl Iterator<Item = &'a Handicraft>
+where
+    I: IntoIterator<Item = &'a Handicraft>,
+{
+    handicrafts
+        .into_iter()
+        .filter(move |handicraft| !excluded.contains(&handicraft.name))
+}
+
+/// Handicrafts that have unlocked by the given island sanctuary rank, to build the graph from
+pub fn unlocked_handicrafts<'a, I>(
+    handicrafts: I,
+    island_rank: usize,
+) -> impl Iterator<Item = &'a Handicraft>
+where
+    I: IntoIterator<Item = &'a Handicraft>,
+{
+    handicrafts
+        .into_iter()
+        .filter(move |handicraft| handicraft.rank <= island_rank)
+}
 
 pub fn create_handicraft_graph<'a, I>(handicrafts: I) -> (HashSet<HandicraftName>, HandicraftGraph)
 where
@@ -59,23 +125,55 @@ where
     (recipe_nodes, graph)
 }
 
+type RareItemCosts = HashMap<HandicraftName, Vec<(MaterialName, usize)>>;
+
+// how many of each rare item each unit of a handicraft uses up
+fn rare_item_costs(
+    handicrafts: &[Handicraft],
+    rare_budget: &HashMap<MaterialName, usize>,
+) -> RareItemCosts {
+    handicrafts
+        .iter()
+        .map(|h| {
+            (
+                h.name,
+                h.materials
+                    .iter()
+                    .filter(|(mat, _)| rare_budget.contains_key(mat))
+                    .map(|(mat, count)| (*mat, *count))
+                    .collect(),
+            )
+        })
+        .collect()
+}
+
+fn can_afford(
+    costs: &[(MaterialName, usize)],
+    units: usize,
+    rare_budget: &HashMap<MaterialName, usize>,
+) -> bool {
+    costs
+        .iter()
+        .all(|(mat, count)| rare_budget.get(mat).copied().unwrap_or_default() >= count * units)
+}
+
 fn remove_unmakeable_recipes(
-    handicrafts: &Vec<Handicraft>,
     recipe_nodes: &mut HashSet<HandicraftName>,
-    rare_item_counts: Vec<RareItemCount>,
+    rare_item_costs: &RareItemCosts,
+    rare_budget: &HashMap<MaterialName, usize>,
+    handicraft_pricing_info: &HashMap<HandicraftName, HandicraftPricingInfo>,
     handicraft_graph: &mut HandicraftGraph,
 ) {
-    let unusable_items: HashSet<_> = rare_item_counts
-        .into_iter()
-        .filter(|item| item.count == 0)
-        .map(|item| *item.name())
-        .collect();
-    for h in handicrafts {
-        if h.materials.keys().any(|mat| unusable_items.contains(mat)) {
-            let node = recipe_nodes.take(&h.name).unwrap_or_else(|| {
+    for (name, costs) in rare_item_costs {
+        let units = handicraft_pricing_info
+            .get(name)
+            .unwrap_or_else(|| panic!("Could not find pricing info for handicraft {:?}", name))
+            .units(false);
+        if !can_afford(costs, units, rare_budget) {
+            let node = recipe_nodes.take(name).unwrap_or_else(|| {
                 panic!(
                     "Rare item node was connected to non-existant recipe ({})",
-                    h.name
+                    name
                 )
             });
             handicraft_graph.remove_node(HandicraftGraphNode::Handicraft(node));
@@ -83,183 +181,1144 @@ fn remove_unmakeable_recipes(
     }
 }
 
+/// The best `top` agendas, best first, that don't use more of any material than the budget has
+#[allow(clippy::too_many_arguments)]
 pub fn find_agendas(
-    handicrafts: &Vec<Handicraft>,
+    handicrafts: &[Handicraft],
     handicraft_pop_supply: HashMap<HandicraftName, PopSupply>,
-    rare_item_counts: Vec<RareItemCount>,
+    rare_budget: MaterialBudget,
     mut recipe_nodes: HashSet<HandicraftName>,
     mut handicraft_graph: HandicraftGraph,
     handicraft_pricing_info: HashMap<HandicraftName, HandicraftPricingInfo>,
-) -> BinaryHeap<Agenda> {
+    modifiers: PricingModifiers,
+    top: usize,
+) -> Vec<Agenda> {
+    let rare_item_costs = rare_item_costs(handicrafts, &rare_budget);
     remove_unmakeable_recipes(
-        handicrafts,
         &mut recipe_nodes,
-        rare_item_counts,
+        &rare_item_costs,
+        &rare_budget,
+        &handicraft_pricing_info,
         &mut handicraft_graph,
     );
 
-    recipe_nodes
+    // no sequence of crafts makes more per hour than the handicraft with the best value per hour
+    let best_rate = recipe_nodes
         .iter()
-        .map(|start| {
-            // max potential number of products per cycle (24 / 4)
-            let mut agenda = Vec::with_capacity(11);
-            agenda.push(*start);
-            generate_agendas(
-                &handicraft_graph,
-                &handicraft_pricing_info,
-                agenda,
-                handicraft_pricing_info
-                    .get(start)
-                    .unwrap_or_else(|| {
-                        panic!("Could not find pricing info for handicraft {:?}", start)
-                    })
-                    .time,
+        .map(|handicraft| {
+            let pricing_info = handicraft_pricing_info.get(handicraft).unwrap_or_else(|| {
+                panic!(
+                    "Could not find pricing info for handicraft {:?}",
+                    handicraft
+                )
+            });
+            (
+                calc_step_value(
+                    *handicraft,
+                    true,
+                    &handicraft_pop_supply,
+                    &handicraft_pricing_info,
+                    modifiers,
+                ),
+                pricing_info.time,
             )
         })
-        .flat_map(IntoIterator::into_iter)
-        .map(|products| calc_agenda(products, &handicraft_pop_supply, &handicraft_pricing_info))
+        .max_by(|(a_value, a_time), (b_value, b_time)| (a_value * b_time).cmp(&(b_value * a_time)))
+        .unwrap_or((0, 1));
+    let search = AgendaSearch {
+        handicraft_graph: &handicraft_graph,
+        handicraft_pricing_info: &handicraft_pricing_info,
+        handicraft_pop_supplies: &handicraft_pop_supply,
+        rare_item_costs: &rare_item_costs,
+        modifiers,
+        best_rate,
+        top,
+    };
+
+    #[cfg(not(feature = "parallel"))]
+    let best = {
+        let mut rare_budget = rare_budget;
+        let mut best = BinaryHeap::with_capacity(top + 1);
+        for start in &recipe_nodes {
+            search.search_from(*start, &mut rare_budget, &mut best);
+        }
+        best
+    };
+    // every start is searched on its own, and the best of each are merged afterwards. agendas are
+    // totally ordered, so the merged heap holds the same agendas the serial search would keep
+    #[cfg(feature = "parallel")]
+    let best = recipe_nodes
+        .par_iter()
+        .map(|start| {
+            let mut rare_budget = rare_budget.clone();
+            let mut best = BinaryHeap::with_capacity(top + 1);
+            search.search_from(*start, &mut rare_budget, &mut best);
+            best
+        })
+        .reduce(BinaryHeap::new, |a, b| search.merge(a, b));
+
+    best.into_sorted_vec()
+        .into_iter()
+        .map(|Reverse(agenda)| agenda)
         .collect()
 }
 
-#[derive(Debug)]
-enum AgendaGeneratorResult {
-    Tail(Vec<HandicraftName>),
-    Intermediate(Vec<AgendaGeneratorResult>),
+struct AgendaSearch<'a> {
+    handicraft_graph: &'a HandicraftGraph,
+    handicraft_pricing_info: &'a HashMap<HandicraftName, HandicraftPricingInfo>,
+    handicraft_pop_supplies: &'a HashMap<HandicraftName, PopSupply>,
+    rare_item_costs: &'a RareItemCosts,
+    modifiers: PricingModifiers,
+    /// best value and time of any efficiency bonus craft, by value per hour
+    best_rate: (usize, usize),
+    top: usize,
 }
 
-impl IntoIterator for AgendaGeneratorResult {
-    type Item = Vec<HandicraftName>;
+impl AgendaSearch<'_> {
+    fn step_value(&self, handicraft: HandicraftName, efficiency_bonus: bool) -> usize {
+        calc_step_value(
+            handicraft,
+            efficiency_bonus,
+            self.handicraft_pop_supplies,
+            self.handicraft_pricing_info,
+            self.modifiers,
+        )
+    }
 
-    type IntoIter = AgendaGeneratorResultIterator;
+    fn search_from(
+        &self,
+        start: HandicraftName,
+        rare_budget: &mut HashMap<MaterialName, usize>,
+        best: &mut BinaryHeap<Reverse<Agenda>>,
+    ) {
+        // max potential number of products per cycle (24 / 4)
+        let mut agenda = Vec::with_capacity(11);
+        agenda.push(start);
+        let mut values = Vec::with_capacity(11);
+        values.push(self.step_value(start, false));
+        let start_info = self
+            .handicraft_pricing_info
+            .get(&start)
+            .unwrap_or_else(|| panic!("Could not find pricing info for handicraft {:?}", start));
+        let start_costs = &self.rare_item_costs[&start];
+        debit(start_costs, start_info.units(false), rare_budget);
+        self.search(&mut agenda, &mut values, start_info.time, rare_budget, best);
+        credit(start_costs, start_info.units(false), rare_budget);
+    }
 
-    fn into_iter(self) -> Self::IntoIter {
-        AgendaGeneratorResultIterator {
-            children: vec![self],
-            parent: None,
+    #[cfg(feature = "parallel")]
+    fn merge(
+        &self,
+        mut a: BinaryHeap<Reverse<Agenda>>,
+        mut b: BinaryHeap<Reverse<Agenda>>,
+    ) -> BinaryHeap<Reverse<Agenda>> {
+        if a.len() < b.len() {
+            std::mem::swap(&mut a, &mut b);
+        }
+        a.append(&mut b);
+        while a.len() > self.top {
+            a.pop();
         }
+        a
     }
-}
 
-// https://aloso.github.io/2021/03/09/creating-an-iterator
-#[derive(Debug, Default)]
-struct AgendaGeneratorResultIterator {
-    children: Vec<AgendaGeneratorResult>,
-    parent: Option<Box<AgendaGeneratorResultIterator>>,
-}
+    // the most the rest of the cycle could add to an agenda
+    fn upper_bound(&self, elapsed: usize) -> usize {
+        let (value, time) = self.best_rate;
+        ((TIME_IN_CYCLE - elapsed) * value).div_ceil(time)
+    }
 
-impl Iterator for AgendaGeneratorResultIterator {
-    type Item = Vec<HandicraftName>;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        match self.children.pop() {
-            Some(AgendaGeneratorResult::Tail(m)) => Some(m),
-            Some(AgendaGeneratorResult::Intermediate(i)) => {
-                *self = AgendaGeneratorResultIterator {
-                    children: i,
-                    parent: Some(Box::new(mem::take(self))),
-                };
-                self.next()
+    // depth first search, adding the agenda to the min-heap of the best agendas once nothing else
+    // fits, and skipping branches that can't catch up to the worst of them
+    fn search(
+        &self,
+        agenda: &mut Vec<HandicraftName>,
+        values: &mut Vec<usize>,
+        elapsed: usize,
+        rare_budget: &mut HashMap<MaterialName, usize>,
+        best: &mut BinaryHeap<Reverse<Agenda>>,
+    ) {
+        let total_value = values.iter().sum();
+        // can't fit anything else in agenda
+        if elapsed > (TIME_IN_CYCLE - MIN_PRODUCT_TIME) {
+            best.push(Reverse(Agenda {
+                handicrafts: agenda.clone(),
+                values: values.clone(),
+                total_value,
+            }));
+            if best.len() > self.top {
+                best.pop();
             }
-            None => match self.parent.take() {
-                Some(p) => {
-                    *self = *p;
-                    self.next()
-                }
-                None => None,
-            },
+            return;
+        }
+        // an agenda worth the same as the worst kept can still outrank it, so only prune below it
+        if best.len() >= self.top
+            && best.peek().is_some_and(|Reverse(worst)| {
+                total_value + self.upper_bound(elapsed) < worst.total_value
+            })
+        {
+            return;
         }
-    }
-}
 
-fn generate_agendas(
-    handicraft_graph: &HandicraftGraph,
-    handicraft_pricing_info: &HashMap<HandicraftName, HandicraftPricingInfo>,
-    agenda: Vec<HandicraftName>,
-    elapsed: usize,
-) -> AgendaGeneratorResult {
-    // can't fit anything else in agenda
-    if elapsed > (TIME_IN_CYCLE - MIN_PRODUCT_TIME) {
-        AgendaGeneratorResult::Tail(agenda)
-    } else {
-        let current = agenda.last().expect("Agenda is empty");
-        let candidates = handicraft_graph
-            .neighbors(HandicraftGraphNode::Handicraft(*current))
+        let current = *agenda.last().expect("Agenda is empty");
+        let mut candidates: Vec<_> = self
+            .handicraft_graph
+            .neighbors(HandicraftGraphNode::Handicraft(current))
             .flat_map(|category| {
-                handicraft_graph.neighbors_directed(category, petgraph::Direction::Incoming)
+                self.handicraft_graph
+                    .neighbors_directed(category, petgraph::Direction::Incoming)
             })
             .map(|recipe| recipe.unwrap_handicraft())
-            .filter(|recipe| current != recipe)
-            .map(|recipe| {
-                (
-                    recipe,
-                    handicraft_pricing_info.get(&recipe).unwrap_or_else(|| {
-                        panic!("Could not find pricing info for handicraft {:?}", recipe)
-                    }),
-                )
-            })
-            .filter(|(_, pricing_info)| pricing_info.time + elapsed <= TIME_IN_CYCLE);
-        AgendaGeneratorResult::Intermediate(
-            candidates
-                .map(|(recipe, pricing_info)| {
-                    let mut new_agenda = agenda.clone();
-                    let elapsed = elapsed + pricing_info.time;
-                    new_agenda.push(recipe);
-                    generate_agendas(
-                        handicraft_graph,
-                        handicraft_pricing_info,
-                        new_agenda,
-                        elapsed,
-                    )
-                })
-                .collect(),
-        )
+            .filter(|recipe| current != *recipe)
+            .collect();
+        // handicrafts sharing more than one category are reached once through each
+        candidates.sort();
+        candidates.dedup();
+        for recipe in candidates {
+            let pricing_info = self
+                .handicraft_pricing_info
+                .get(&recipe)
+                .unwrap_or_else(|| {
+                    panic!("Could not find pricing info for handicraft {:?}", recipe)
+                });
+            // don't use more of a rare item than is left over from the earlier steps. every step
+            // after the first gets the efficiency bonus
+            let costs = &self.rare_item_costs[&recipe];
+            let units = pricing_info.units(true);
+            if pricing_info.time + elapsed > TIME_IN_CYCLE || !can_afford(costs, units, rare_budget)
+            {
+                continue;
+            }
+            debit(costs, units, rare_budget);
+            agenda.push(recipe);
+            values.push(self.step_value(recipe, true));
+            self.search(
+                agenda,
+                values,
+                elapsed + pricing_info.time,
+                rare_budget,
+                best,
+            );
+            values.pop();
+            agenda.pop();
+            credit(costs, units, rare_budget);
+        }
     }
 }
 
-fn calc_agenda(
-    agenda: Vec<HandicraftName>,
+fn debit(
+    costs: &[(MaterialName, usize)],
+    units: usize,
+    rare_budget: &mut HashMap<MaterialName, usize>,
+) {
+    for (mat, count) in costs {
+        *rare_budget
+            .get_mut(mat)
+            .expect("Rare item missing from budget") -= count * units;
+    }
+}
+
+fn credit(
+    costs: &[(MaterialName, usize)],
+    units: usize,
+    rare_budget: &mut HashMap<MaterialName, usize>,
+) {
+    for (mat, count) in costs {
+        *rare_budget
+            .get_mut(mat)
+            .expect("Rare item missing from budget") += count * units;
+    }
+}
+
+fn calc_step_value(
+    handicraft: HandicraftName,
+    efficiency_bonus: bool,
     handicraft_pop_supplies: &HashMap<HandicraftName, PopSupply>,
     handicraft_pricing_info: &HashMap<HandicraftName, HandicraftPricingInfo>,
-) -> Agenda {
-    let pricing: Vec<_> = agenda
+    modifiers: PricingModifiers,
+) -> usize {
+    calc_abs_pricing(
+        efficiency_bonus,
+        *handicraft_pop_supplies.get(&handicraft).unwrap_or_else(|| {
+            panic!(
+                "Agenda had handicraft without popularity/supply ({})",
+                handicraft
+            )
+        }),
+        *handicraft_pricing_info.get(&handicraft).unwrap_or_else(|| {
+            panic!(
+                "Agenda had handicraft without pricing info ({})",
+                handicraft
+            )
+        }),
+        modifiers,
+    )
+}
+
+// crafts are paid out when they finish, using the groove from before any craft finishing in that
+// same hour (in any workshop) has added to it
+pub fn calc_agendas_with_groove(
+    workshops: Vec<Vec<HandicraftName>>,
+    handicraft_pop_supplies: &HashMap<HandicraftName, PopSupply>,
+    handicraft_pricing_info: &HashMap<HandicraftName, HandicraftPricingInfo>,
+    workshop_rank: WorkshopRank,
+    mut groove: Groove,
+) -> GrooveAgendas {
+    let mut steps: Vec<_> = workshops
         .iter()
-        .zip(iter::once(false).chain(iter::repeat(true)))
-        .map(|(handicraft, efficiency_bonus)| {
-            calc_abs_pricing(
+        .enumerate()
+        .flat_map(|(workshop, agenda)| {
+            agenda
+                .iter()
+                .enumerate()
+                .scan(0, move |finished, (step, handicraft)| {
+                    let pricing_info =
+                        *handicraft_pricing_info.get(handicraft).unwrap_or_else(|| {
+                            panic!(
+                                "Agenda had handicraft without pricing info ({})",
+                                handicraft
+                            )
+                        });
+                    *finished += pricing_info.time;
+                    Some((*finished, workshop, step, *handicraft, pricing_info))
+                })
+        })
+        .collect();
+    steps.sort_by_key(|(finished, workshop, step, _, _)| (*finished, *workshop, *step));
+
+    let mut values: Vec<_> = workshops
+        .iter()
+        .map(|agenda| vec![0; agenda.len()])
+        .collect();
+    let mut steps = steps.into_iter().peekable();
+    while let Some(&(hour, ..)) = steps.peek() {
+        let modifiers = PricingModifiers {
+            workshop_rank,
+            groove: groove.value,
+        };
+        let mut bonuses = 0;
+        while let Some((_, workshop, step, handicraft, pricing_info)) =
+            steps.next_if(|(finished, ..)| *finished == hour)
+        {
+            let efficiency_bonus = step > 0;
+            values[workshop][step] = calc_abs_pricing(
                 efficiency_bonus,
-                *handicraft_pop_supplies.get(handicraft).unwrap_or_else(|| {
+                *handicraft_pop_supplies.get(&handicraft).unwrap_or_else(|| {
                     panic!(
                         "Agenda had handicraft without popularity/supply ({})",
                         handicraft
                     )
                 }),
-                *handicraft_pricing_info.get(handicraft).unwrap_or_else(|| {
-                    panic!(
-                        "Agenda had handicraft without pricing info ({})",
-                        handicraft
-                    )
-                }),
-            )
+                pricing_info,
+                modifiers,
+            );
+            if efficiency_bonus {
+                bonuses += 1;
+            }
+        }
+        for _ in 0..bonuses {
+            groove.increment();
+        }
+    }
+
+    let agendas: Vec<_> = workshops
+        .into_iter()
+        .zip(values)
+        .map(|(handicrafts, values)| Agenda {
+            handicrafts,
+            total_value: values.iter().sum(),
+            values,
         })
         .collect();
-    Agenda {
-        handicrafts: agenda,
-        total_value: pricing.iter().sum(),
-        values: pricing,
+    GrooveAgendas {
+        total_value: agendas.iter().map(|agenda| agenda.total_value).sum(),
+        agendas,
+        groove,
+    }
+}
+
+/// Why an agenda can't be worked, numbering steps from 1
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum AgendaError {
+    #[error("The agenda has no steps")]
+    Empty,
+    #[error("Step {step}: {handicraft} isn't one of the handicrafts being planned with")]
+    UnknownHandicraft {
+        step: usize,
+        handicraft: HandicraftName,
+    },
+    #[error(
+        "Step {step}: {handicraft} finishes after {hours} hours, past the end of the cycle ({TIME_IN_CYCLE} hours)"
+    )]
+    TooLong {
+        step: usize,
+        handicraft: HandicraftName,
+        hours: usize,
+    },
+    #[error("Step {step}: {handicraft} shares no category with {previous}")]
+    NoSharedCategory {
+        step: usize,
+        handicraft: HandicraftName,
+        previous: HandicraftName,
+    },
+    #[error("Step {step}: {handicraft} is made twice in a row, which gets no efficiency bonus")]
+    RepeatedHandicraft {
+        step: usize,
+        handicraft: HandicraftName,
+    },
+}
+
+/// Checks that an agenda fits in a cycle and that every step after the first gets the efficiency
+/// bonus, so shares a category with the step before it without repeating it
+pub fn check_agenda(
+    handicrafts: &[Handicraft],
+    agenda: &[HandicraftName],
+) -> Result<(), AgendaError> {
+    if agenda.is_empty() {
+        return Err(AgendaError::Empty);
     }
+    let mut hours = 0;
+    let mut previous: Option<&Handicraft> = None;
+    for (i, name) in agenda.iter().enumerate() {
+        let step = i + 1;
+        let handicraft =
+            find_handicraft(handicrafts, name).ok_or(AgendaError::UnknownHandicraft {
+                step,
+                handicraft: *name,
+            })?;
+        hours += handicraft.time;
+        if hours > TIME_IN_CYCLE {
+            return Err(AgendaError::TooLong {
+                step,
+                handicraft: *name,
+                hours,
+            });
+        }
+        if let Some(previous) = previous {
+            if previous.name == *name {
+                return Err(AgendaError::RepeatedHandicraft {
+                    step,
+                    handicraft: *name,
+                });
+            }
+            if !previous.gives_bonus_to(handicraft) {
+                return Err(AgendaError::NoSharedCategory {
+                    step,
+                    handicraft: *name,
+                    previous: previous.name,
+                });
+            }
+        }
+        previous = Some(handicraft);
+    }
+    Ok(())
+}
+
+/// Prices an agenda chosen by hand once `check_agenda` has passed it, as a day with one workshop
+/// so that the groove it builds is counted the same way as in a day plan
+pub fn evaluate(
+    handicrafts: &[Handicraft],
+    agenda: &[HandicraftName],
+    handicraft_pop_supplies: &HashMap<HandicraftName, PopSupply>,
+    workshop_rank: WorkshopRank,
+    groove: Groove,
+) -> Result<GrooveAgendas, AgendaError> {
+    check_agenda(handicrafts, agenda)?;
+    let pricing_info = agenda
+        .iter()
+        .map(|name| (*name, agenda_step(handicrafts, name).as_pricing_info()))
+        .collect();
+    Ok(calc_agendas_with_groove(
+        vec![agenda.to_vec()],
+        handicraft_pop_supplies,
+        &pricing_info,
+        workshop_rank,
+        groove,
+    ))
 }
 
-// full pricing formula uses workshop level and groove, which is TODO
-// for now, just the basic formula
+fn as_percent(multiplier: f64) -> usize {
+    (multiplier * 100.0).round() as usize
+}
+
+// the game rounds down after applying workshop rank and groove to the base value, then again
+// after popularity and supply, and only then multiplies by the units made. percentages are
+// used instead of the float multipliers so that exact products don't get floored one under
 #[memoize]
 fn calc_abs_pricing(
     efficiency_bonus: bool,
     pop_supply: PopSupply,
     handicraft: HandicraftPricingInfo,
+    modifiers: PricingModifiers,
 ) -> usize {
-    (if efficiency_bonus { 2 } else { 1 })
-        * (pop_supply.popularity.multiplier()
-            * pop_supply.supply.multiplier()
-            * (handicraft.value as f64).floor())
-        .floor() as usize
+    let base = handicraft.value
+        * as_percent(modifiers.workshop_rank.multiplier())
+        * (100 + modifiers.groove)
+        / (100 * 100);
+    let demand = base
+        * as_percent(pop_supply.popularity.multiplier())
+        * as_percent(pop_supply.supply.multiplier())
+        / (100 * 100);
+    handicraft.units(efficiency_bonus) * demand
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{iter, mem};
+
+    use super::*;
+    use crate::input::PlanInput;
+    use crate::testing::{self, handicraft, material, pop_supplies, pricing_info};
+    use crate::types::{material_budget, Popularity, RareItemCount, Supply, WorkshopRank};
+
+    // the exhaustive search that find_agendas replaced, kept to check it against
+
+    #[derive(Debug)]
+    enum AgendaGeneratorResult {
+        Tail(Vec<HandicraftName>),
+        Intermediate(Vec<AgendaGeneratorResult>),
+    }
+
+    impl IntoIterator for AgendaGeneratorResult {
+        type Item = Vec<HandicraftName>;
+
+        type IntoIter = AgendaGeneratorResultIterator;
+
+        fn into_iter(self) -> Self::IntoIter {
+            AgendaGeneratorResultIterator {
+                children: vec![self],
+                parent: None,
+            }
+        }
+    }
+
+    // https://aloso.github.io/2021/03/09/creating-an-iterator
+    #[derive(Debug, Default)]
+    struct AgendaGeneratorResultIterator {
+        children: Vec<AgendaGeneratorResult>,
+        parent: Option<Box<AgendaGeneratorResultIterator>>,
+    }
+
+    impl Iterator for AgendaGeneratorResultIterator {
+        type Item = Vec<HandicraftName>;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            match self.children.pop() {
+                Some(AgendaGeneratorResult::Tail(m)) => Some(m),
+                Some(AgendaGeneratorResult::Intermediate(i)) => {
+                    *self = AgendaGeneratorResultIterator {
+                        children: i,
+                        parent: Some(Box::new(mem::take(self))),
+                    };
+                    self.next()
+                }
+                None => match self.parent.take() {
+                    Some(p) => {
+                        *self = *p;
+                        self.next()
+                    }
+                    None => None,
+                },
+            }
+        }
+    }
+
+    fn generate_agendas(
+        handicraft_graph: &HandicraftGraph,
+        handicraft_pricing_info: &HashMap<HandicraftName, HandicraftPricingInfo>,
+        rare_item_costs: &RareItemCosts,
+        rare_budget: &mut HashMap<MaterialName, usize>,
+        agenda: Vec<HandicraftName>,
+        elapsed: usize,
+    ) -> AgendaGeneratorResult {
+        // can't fit anything else in agenda
+        if elapsed > (TIME_IN_CYCLE - MIN_PRODUCT_TIME) {
+            AgendaGeneratorResult::Tail(agenda)
+        } else {
+            let current = agenda.last().expect("Agenda is empty");
+            let candidates: Vec<_> = handicraft_graph
+                .neighbors(HandicraftGraphNode::Handicraft(*current))
+                .flat_map(|category| {
+                    handicraft_graph.neighbors_directed(category, petgraph::Direction::Incoming)
+                })
+                .map(|recipe| recipe.unwrap_handicraft())
+                .filter(|recipe| current != recipe)
+                .map(|recipe| {
+                    (
+                        recipe,
+                        handicraft_pricing_info.get(&recipe).unwrap_or_else(|| {
+                            panic!("Could not find pricing info for handicraft {:?}", recipe)
+                        }),
+                    )
+                })
+                .filter(|(_, pricing_info)| pricing_info.time + elapsed <= TIME_IN_CYCLE)
+                // don't use more of a rare item than is left over from the earlier steps. every step
+                // after the first gets the efficiency bonus
+                .filter(|(recipe, pricing_info)| {
+                    can_afford(
+                        &rare_item_costs[recipe],
+                        pricing_info.units(true),
+                        rare_budget,
+                    )
+                })
+                .collect();
+            AgendaGeneratorResult::Intermediate(
+                candidates
+                    .into_iter()
+                    .map(|(recipe, pricing_info)| {
+                        let mut new_agenda = agenda.clone();
+                        let elapsed = elapsed + pricing_info.time;
+                        new_agenda.push(recipe);
+                        let costs = &rare_item_costs[&recipe];
+                        debit(costs, pricing_info.units(true), rare_budget);
+                        let generated = generate_agendas(
+                            handicraft_graph,
+                            handicraft_pricing_info,
+                            rare_item_costs,
+                            rare_budget,
+                            new_agenda,
+                            elapsed,
+                        );
+                        credit(costs, pricing_info.units(true), rare_budget);
+                        generated
+                    })
+                    .collect(),
+            )
+        }
+    }
+
+    fn calc_agenda(
+        agenda: Vec<HandicraftName>,
+        handicraft_pop_supplies: &HashMap<HandicraftName, PopSupply>,
+        handicraft_pricing_info: &HashMap<HandicraftName, HandicraftPricingInfo>,
+        modifiers: PricingModifiers,
+    ) -> Agenda {
+        let pricing: Vec<_> = agenda
+            .iter()
+            .zip(iter::once(false).chain(iter::repeat(true)))
+            .map(|(handicraft, efficiency_bonus)| {
+                calc_abs_pricing(
+                    efficiency_bonus,
+                    *handicraft_pop_supplies.get(handicraft).unwrap_or_else(|| {
+                        panic!(
+                            "Agenda had handicraft without popularity/supply ({})",
+                            handicraft
+                        )
+                    }),
+                    *handicraft_pricing_info.get(handicraft).unwrap_or_else(|| {
+                        panic!(
+                            "Agenda had handicraft without pricing info ({})",
+                            handicraft
+                        )
+                    }),
+                    modifiers,
+                )
+            })
+            .collect();
+        Agenda {
+            handicrafts: agenda,
+            total_value: pricing.iter().sum(),
+            values: pricing,
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn find_agendas_exhaustive(
+        handicrafts: &[Handicraft],
+        handicraft_pop_supply: HashMap<HandicraftName, PopSupply>,
+        mut rare_budget: MaterialBudget,
+        mut recipe_nodes: HashSet<HandicraftName>,
+        mut handicraft_graph: HandicraftGraph,
+        handicraft_pricing_info: HashMap<HandicraftName, HandicraftPricingInfo>,
+        modifiers: PricingModifiers,
+        top: usize,
+    ) -> Vec<Agenda> {
+        let rare_item_costs = rare_item_costs(handicrafts, &rare_budget);
+        remove_unmakeable_recipes(
+            &mut recipe_nodes,
+            &rare_item_costs,
+            &rare_budget,
+            &handicraft_pricing_info,
+            &mut handicraft_graph,
+        );
+
+        let mut agendas: Vec<_> = recipe_nodes
+            .iter()
+            .map(|start| {
+                let start_info = handicraft_pricing_info[start];
+                let start_costs = &rare_item_costs[start];
+                debit(start_costs, start_info.units(false), &mut rare_budget);
+                let generated = generate_agendas(
+                    &handicraft_graph,
+                    &handicraft_pricing_info,
+                    &rare_item_costs,
+                    &mut rare_budget,
+                    vec![*start],
+                    start_info.time,
+                );
+                credit(start_costs, start_info.units(false), &mut rare_budget);
+                generated
+            })
+            .flat_map(IntoIterator::into_iter)
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .map(|products| {
+                calc_agenda(
+                    products,
+                    &handicraft_pop_supply,
+                    &handicraft_pricing_info,
+                    modifiers,
+                )
+            })
+            .collect();
+        agendas.sort_by(|a, b| b.cmp(a));
+        agendas.truncate(top);
+        agendas
+    }
+
+    fn assert_matches_exhaustive(
+        pop_supply: impl Fn(usize) -> PopSupply,
+        rare_count: usize,
+        inventory: &[(&str, usize)],
+        modifiers: PricingModifiers,
+        top: usize,
+    ) {
+        let data = testing::data();
+        let handicraft_pop_supply: HashMap<_, _> = data
+            .handicrafts
+            .iter()
+            .enumerate()
+            .map(|(i, handicraft)| (handicraft.name, pop_supply(i)))
+            .collect();
+        let rare_item_counts: Vec<_> = data
+            .rare_items()
+            .map(|rare| RareItemCount {
+                rare,
+                count: rare_count,
+            })
+            .collect();
+        let inventory = inventory
+            .iter()
+            .map(|(name, count)| (material(name), *count))
+            .collect();
+        let budget = material_budget(&rare_item_counts, &inventory);
+        let handicraft_pricing_info = pricing_info(&data.handicrafts);
+        let (recipe_nodes, handicraft_graph) = create_handicraft_graph(data.handicrafts.iter());
+
+        assert_eq!(
+            find_agendas(
+                &data.handicrafts,
+                handicraft_pop_supply.clone(),
+                budget.clone(),
+                recipe_nodes.clone(),
+                handicraft_graph.clone(),
+                handicraft_pricing_info.clone(),
+                modifiers,
+                top,
+            ),
+            find_agendas_exhaustive(
+                &data.handicrafts,
+                handicraft_pop_supply,
+                budget,
+                recipe_nodes,
+                handicraft_graph,
+                handicraft_pricing_info,
+                modifiers,
+                top,
+            )
+        );
+    }
+
+    #[test]
+    fn branch_and_bound_matches_exhaustive_search() {
+        let popularities = [
+            Popularity::Low,
+            Popularity::Average,
+            Popularity::High,
+            Popularity::VeryHigh,
+        ];
+        let supplies = [
+            Supply::Nonexistent,
+            Supply::Insufficient,
+            Supply::Sufficient,
+            Supply::Surplus,
+            Supply::Overflowing,
+        ];
+        assert_matches_exhaustive(
+            |_| PopSupply {
+                popularity: Popularity::Average,
+                supply: Supply::Sufficient,
+            },
+            0,
+            &[],
+            PricingModifiers::default(),
+            50,
+        );
+        assert_matches_exhaustive(
+            |i| PopSupply {
+                popularity: popularities[i % popularities.len()],
+                supply: supplies[i % supplies.len()],
+            },
+            4,
+            &[],
+            PricingModifiers {
+                workshop_rank: WorkshopRank::Three,
+                groove: 12,
+            },
+            100,
+        );
+        assert_matches_exhaustive(
+            |i| PopSupply {
+                popularity: popularities[(i * 7) % popularities.len()],
+                supply: supplies[(i * 3) % supplies.len()],
+            },
+            10,
+            &[("IslandSap", 6), ("IslandCopperOre", 3), ("IslandLog", 10)],
+            PricingModifiers {
+                workshop_rank: WorkshopRank::Five,
+                groove: 35,
+            },
+            20,
+        );
+    }
+
+    #[test]
+    fn material_graph_queries() {
+        let data = testing::data();
+        let (_, graph) = create_material_graph(data.handicrafts.iter());
+        let islewort = material("Islewort");
+        let sap = material("IslandSap");
+        let using = |material| {
+            data.handicrafts
+                .iter()
+                .filter(|handicraft| handicraft.materials.contains_key(&material))
+                .map(|handicraft| handicraft.name)
+                .collect::<HashSet<_>>()
+        };
+
+        let uses_islewort = handicrafts_using(&graph, islewort);
+        assert!(uses_islewort.contains(&handicraft("IsleworksPotion")));
+        assert_eq!(uses_islewort, using(islewort));
+
+        assert_eq!(
+            shared_materials(
+                &graph,
+                handicraft("IsleworksPotion"),
+                handicraft("IsleworksFiresand")
+            ),
+            HashSet::from([islewort])
+        );
+        assert!(shared_materials(
+            &graph,
+            handicraft("IsleworksPotion"),
+            handicraft("IsleworksPotion")
+        )
+        .contains(&material("IslandPalmLeaf")));
+
+        assert_eq!(
+            unmakeable_without(&graph, [islewort, sap]),
+            &using(islewort) | &using(sap)
+        );
+        assert!(unmakeable_without(&graph, []).is_empty());
+    }
+
+    #[test]
+    fn agendas_leave_out_unmakeable_handicrafts() {
+        let data = testing::data();
+        let (_, material_graph) = create_material_graph(data.handicrafts.iter());
+        let unmakeable = unmakeable_without(&material_graph, [material("Islewort")]);
+        let handicrafts: Vec<_> = excluding_handicrafts(&data.handicrafts, &unmakeable)
+            .cloned()
+            .collect();
+        assert_eq!(handicrafts.len(), data.handicrafts.len() - unmakeable.len());
+
+        let (recipe_nodes, handicraft_graph) = create_handicraft_graph(handicrafts.iter());
+        let agendas = find_agendas(
+            &handicrafts,
+            pop_supplies(&handicrafts, Popularity::Average, Supply::Sufficient),
+            MaterialBudget::new(),
+            recipe_nodes,
+            handicraft_graph,
+            pricing_info(&handicrafts),
+            PricingModifiers::default(),
+            20,
+        );
+        assert_eq!(agendas.len(), 20);
+        assert!(agendas
+            .iter()
+            .flat_map(|agenda| &agenda.handicrafts)
+            .all(|name| !unmakeable.contains(name)));
+    }
+
+    #[test]
+    fn agendas_only_use_handicrafts_unlocked_at_the_island_rank() {
+        let data = testing::data();
+        let island_rank = 5;
+        let handicrafts = PlanInput {
+            island_rank: Some(island_rank),
+            ..PlanInput::default()
+        }
+        .handicrafts(data);
+        let locked: Vec<_> = data
+            .handicrafts
+            .iter()
+            .filter(|handicraft| handicraft.rank > island_rank)
+            .collect();
+        assert!(!locked.is_empty());
+        assert_eq!(handicrafts.len(), data.handicrafts.len() - locked.len());
+
+        let (recipe_nodes, handicraft_graph) = create_handicraft_graph(handicrafts.iter());
+        let agendas = find_agendas(
+            &handicrafts,
+            pop_supplies(&handicrafts, Popularity::Average, Supply::Sufficient),
+            MaterialBudget::new(),
+            recipe_nodes,
+            handicraft_graph,
+            pricing_info(&handicrafts),
+            PricingModifiers::default(),
+            100,
+        );
+        assert_eq!(agendas.len(), 100);
+        for name in agendas.iter().flat_map(|agenda| &agenda.handicrafts) {
+            assert!(
+                agenda_step(&data.handicrafts, name).rank <= island_rank,
+                "{} is locked",
+                name
+            );
+        }
+
+        let locked = locked[0].name;
+        assert_eq!(
+            evaluate(
+                &handicrafts,
+                &[locked],
+                &pop_supplies(&data.handicrafts, Popularity::Average, Supply::Sufficient),
+                WorkshopRank::Three,
+                Groove::new(0, 35),
+            ),
+            Err(AgendaError::UnknownHandicraft {
+                step: 1,
+                handicraft: locked,
+            })
+        );
+    }
+
+    #[test]
+    fn evaluate_prices_agendas_like_the_day_plan() {
+        let data = testing::data();
+        let pop_supplies = pop_supplies(&data.handicrafts, Popularity::High, Supply::Insufficient);
+        let pricing_info = pricing_info(&data.handicrafts);
+        // at the cap groove can't build, so the search's prices are what the day plan pays
+        let groove = Groove::new(35, 35);
+        let modifiers = PricingModifiers {
+            workshop_rank: WorkshopRank::Four,
+            groove: groove.value,
+        };
+        let (recipe_nodes, handicraft_graph) = create_handicraft_graph(data.handicrafts.iter());
+        let agendas = find_agendas(
+            &data.handicrafts,
+            pop_supplies.clone(),
+            MaterialBudget::new(),
+            recipe_nodes,
+            handicraft_graph,
+            pricing_info.clone(),
+            modifiers,
+            10,
+        );
+        for agenda in agendas {
+            let evaluate = |groove| {
+                evaluate(
+                    &data.handicrafts,
+                    &agenda.handicrafts,
+                    &pop_supplies,
+                    WorkshopRank::Four,
+                    groove,
+                )
+                .unwrap()
+            };
+            assert_eq!(evaluate(groove).agendas, std::slice::from_ref(&agenda));
+            // below it, later steps are paid the groove the earlier ones built
+            let building = evaluate(Groove::new(10, 35));
+            assert_eq!(
+                building,
+                calc_agendas_with_groove(
+                    vec![agenda.handicrafts.clone()],
+                    &pop_supplies,
+                    &pricing_info,
+                    WorkshopRank::Four,
+                    Groove::new(10, 35),
+                )
+            );
+            assert_eq!(building.groove.value, 10 + agenda.handicrafts.len() - 1);
+        }
+
+        let potion = handicraft("IsleworksPotion");
+        let firesand = handicraft("IsleworksFiresand");
+        assert_eq!(
+            evaluate(
+                &data.handicrafts,
+                &[potion, firesand, potion, firesand, potion, firesand, potion],
+                &pop_supplies,
+                WorkshopRank::Four,
+                groove
+            ),
+            Err(AgendaError::TooLong {
+                step: 7,
+                handicraft: potion,
+                hours: 28,
+            })
+        );
+        assert_eq!(
+            evaluate(
+                &data.handicrafts,
+                &[potion, firesand, firesand],
+                &pop_supplies,
+                WorkshopRank::Four,
+                groove
+            ),
+            Err(AgendaError::RepeatedHandicraft {
+                step: 3,
+                handicraft: firesand,
+            })
+        );
+        let unrelated = data
+            .handicrafts
+            .iter()
+            .find(|handicraft| {
+                !handicraft
+                    .category
+                    .iter()
+                    .any(|category| data.handicrafts[1].category.contains(category))
+            })
+            .unwrap()
+            .name;
+        assert_eq!(
+            evaluate(
+                &data.handicrafts,
+                &[potion, firesand, unrelated],
+                &pop_supplies,
+                WorkshopRank::Four,
+                groove
+            ),
+            Err(AgendaError::NoSharedCategory {
+                step: 3,
+                handicraft: unrelated,
+                previous: firesand,
+            })
+        );
+        // agendas that weren't checked only get the bonus where steps are linked
+        let unlinked = Agenda {
+            handicrafts: vec![potion, potion, firesand, unrelated],
+            values: vec![0; 4],
+            total_value: 0,
+        };
+        assert_eq!(
+            unlinked.efficiency_bonuses(&data.handicrafts),
+            [false, false, true, false]
+        );
+        assert_eq!(
+            evaluate(
+                &data.handicrafts,
+                &[],
+                &pop_supplies,
+                WorkshopRank::Four,
+                groove
+            ),
+            Err(AgendaError::Empty)
+        );
+    }
+
+    #[test]
+    fn prices_round_down_before_counting_units() {
+        let pricing = |value, quantity, rank, groove, popularity, supply, efficiency_bonus| {
+            calc_abs_pricing(
+                efficiency_bonus,
+                PopSupply { popularity, supply },
+                HandicraftPricingInfo {
+                    time: 4,
+                    quantity,
+                    value,
+                },
+                PricingModifiers {
+                    workshop_rank: rank,
+                    groove,
+                },
+            )
+        };
+        use Popularity::*;
+        use Supply::*;
+        use WorkshopRank::*;
+
+        // 28 * 1.2 * 1.05 = 35.28 -> 35, then 35 * 1.2 * 1.3 = 54.6 -> 54, made twice. Rounding
+        // once at the end would give 110
+        assert_eq!(pricing(28, 1, Three, 5, High, Insufficient, true), 108);
+        assert_eq!(pricing(28, 1, Three, 5, High, Insufficient, false), 54);
+        // 40 * 1.4 * 1.35 = 75.6 -> 75, then 75 * 1.2 = 90, for 4 units
+        assert_eq!(pricing(40, 2, Five, 35, High, Sufficient, true), 360);
+        // 25 * 1.1 = 27.5 -> 27, then 27 * 1.4 * 0.8 = 30.24 -> 30
+        assert_eq!(pricing(25, 1, Two, 0, VeryHigh, Surplus, false), 30);
+        // products that come out exact aren't floored one under
+        assert_eq!(pricing(50, 1, One, 0, Average, Overflowing, false), 30);
+        assert_eq!(pricing(10, 1, Two, 0, Average, Sufficient, false), 11);
+        assert_eq!(pricing(10, 1, Four, 0, Low, Overflowing, false), 6);
+    }
+
+    #[test]
+    fn groove_builds_across_workshops() {
+        let data = testing::data();
+        let potion = handicraft("IsleworksPotion");
+        let firesand = handicraft("IsleworksFiresand");
+        let pop_supplies = pop_supplies(&data.handicrafts, Popularity::Average, Supply::Sufficient);
+        let pricing_info = pricing_info(&data.handicrafts);
+        let price = |handicraft, efficiency_bonus, groove| {
+            calc_abs_pricing(
+                efficiency_bonus,
+                pop_supplies[&handicraft],
+                pricing_info[&handicraft],
+                PricingModifiers {
+                    workshop_rank: WorkshopRank::Three,
+                    groove,
+                },
+            )
+        };
+        // both take 4 hours, so the workshops finish crafts at hours 4 and 8 together
+        let workshops = vec![vec![potion, firesand, potion], vec![firesand, potion]];
+        let day = |groove| {
+            calc_agendas_with_groove(
+                workshops.clone(),
+                &pop_supplies,
+                &pricing_info,
+                WorkshopRank::Three,
+                groove,
+            )
+        };
+
+        // the first crafts get no efficiency bonus, so only the three after them add groove
+        assert_eq!(day(Groove::new(0, 35)).groove.value, 3);
+
+        let capped = day(Groove::new(33, 35));
+        assert_eq!(capped.groove.value, 35);
+        // crafts finishing at hour 8 are both paid at the groove from before either of them
+        assert_eq!(
+            capped.agendas[0].values,
+            vec![
+                price(potion, false, 33),
+                price(firesand, true, 33),
+                price(potion, true, 35),
+            ]
+        );
+        assert_eq!(
+            capped.agendas[1].values,
+            vec![price(firesand, false, 33), price(potion, true, 33)]
+        );
+        assert_ne!(price(potion, true, 33), price(potion, true, 35));
+        assert_eq!(
+            capped.total_value,
+            capped
+                .agendas
+                .iter()
+                .map(|agenda| agenda.total_value)
+                .sum::<usize>()
+        );
+    }
 }
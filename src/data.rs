@@ -0,0 +1,225 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::name::interning;
+use crate::types::{HandicraftName, WorkshopData, WorkshopDataReadError};
+
+/// Environment variable naming a data file or directory, used when none is given on the command
+/// line
+pub const DATA_ENV_VAR: &str = "MJI_AGENDA_DATA";
+const DATA_FILE_NAME: &str = "handicrafts.toml";
+const OVERLAY_DIR_NAME: &str = "overlays";
+const CONFIG_DIR_NAME: &str = "mji-agenda";
+
+/// Where to read workshop data from: the given path, then the path in `MJI_AGENDA_DATA`, then
+/// the per-user config directory if it exists. None means the embedded copy
+pub fn data_path(path: Option<&Path>) -> Option<PathBuf> {
+    path.map(Path::to_path_buf)
+        .or_else(|| env::var_os(DATA_ENV_VAR).map(PathBuf::from))
+        .or_else(|| config_dir().filter(|dir| dir.is_dir()))
+}
+
+// $XDG_CONFIG_HOME/mji-agenda, falling back to ~/.config/mji-agenda, or %APPDATA%\mji-agenda
+fn config_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))
+        .map(|dir| dir.join(CONFIG_DIR_NAME))
+}
+
+/// Reads workshop data from wherever `data_path` points, then patches it with each overlay in
+/// turn.
+///
+/// The path can be a whole data file, or a directory holding an optional `handicrafts.toml` (the
+/// embedded copy is used without one) and an `overlays` directory whose `.toml` files are applied
+/// in name order before the given overlays. An overlay lists `[[handicrafts]]` entries: fields
+/// given for a handicraft already in the data replace its own, and handicrafts that aren't are
+/// added
+pub fn load_workshop_data(
+    path: Option<&Path>,
+    overlays: &[PathBuf],
+) -> Result<WorkshopData, WorkshopDataReadError> {
+    let mut overlay_paths = Vec::new();
+    let mut data = match data_path(path) {
+        Some(path) if path.is_dir() => {
+            let overlay_dir = path.join(OVERLAY_DIR_NAME);
+            if overlay_dir.is_dir() {
+                overlay_paths = toml_files(&overlay_dir)?;
+            }
+            let data_file = path.join(DATA_FILE_NAME);
+            if data_file.is_file() {
+                read_value(&data_file)?
+            } else {
+                embedded()?
+            }
+        }
+        Some(path) => read_value(&path)?,
+        None => embedded()?,
+    };
+    overlay_paths.extend(overlays.iter().cloned());
+    for overlay in &overlay_paths {
+        apply_overlay(&mut data, read_value(overlay)?, overlay)?;
+    }
+    let data: WorkshopData =
+        interning(|| data.try_into()).map_err(WorkshopDataReadError::Malformed)?;
+    data.validate()?;
+    Ok(data)
+}
+
+pub(crate) fn embedded() -> Result<toml::Value, WorkshopDataReadError> {
+    let raw = include_bytes!("handicrafts.toml");
+    String::from_utf8_lossy(raw)
+        .parse()
+        .map_err(WorkshopDataReadError::Toml)
+}
+
+fn read_value(path: &Path) -> Result<toml::Value, WorkshopDataReadError> {
+    fs::read_to_string(path)
+        .map_err(|source| WorkshopDataReadError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?
+        .parse()
+        .map_err(WorkshopDataReadError::Toml)
+}
+
+fn toml_files(dir: &Path) -> Result<Vec<PathBuf>, WorkshopDataReadError> {
+    let read_error = |source| WorkshopDataReadError::Io {
+        path: dir.to_path_buf(),
+        source,
+    };
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).map_err(read_error)? {
+        let path = entry.map_err(read_error)?.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "toml") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn apply_overlay(
+    data: &mut toml::Value,
+    overlay: toml::Value,
+    path: &Path,
+) -> Result<(), WorkshopDataReadError> {
+    let invalid = |reason: &str| WorkshopDataReadError::Overlay {
+        path: path.to_path_buf(),
+        reason: reason.to_string(),
+    };
+    let toml::Value::Table(mut overlay) = overlay else {
+        return Err(invalid("not a table"));
+    };
+    let patches = match overlay.remove("handicrafts") {
+        Some(toml::Value::Array(patches)) => patches,
+        Some(_) => return Err(invalid("handicrafts is not an array of tables")),
+        None => Vec::new(),
+    };
+    if let Some(key) = overlay.keys().next() {
+        return Err(invalid(&format!(
+            "only handicrafts can be patched, not {}",
+            key
+        )));
+    }
+    let handicrafts = data
+        .get_mut("handicrafts")
+        .and_then(toml::Value::as_array_mut)
+        .ok_or_else(|| invalid("the data it patches has no handicrafts"))?;
+
+    for patch in patches {
+        let toml::Value::Table(patch) = patch else {
+            return Err(invalid("handicrafts is not an array of tables"));
+        };
+        let name = patch
+            .get("name")
+            .and_then(toml::Value::as_str)
+            .map(HandicraftName::intern)
+            .ok_or_else(|| invalid("handicraft entry has no name"))?;
+        let existing = handicrafts.iter_mut().find_map(|handicraft| {
+            let same_name = handicraft
+                .get("name")
+                .and_then(toml::Value::as_str)
+                .is_some_and(|other| HandicraftName::intern(other) == name);
+            if same_name {
+                handicraft.as_table_mut()
+            } else {
+                None
+            }
+        });
+        match existing {
+            Some(handicraft) => handicraft.extend(patch),
+            None => handicrafts.push(toml::Value::Table(patch)),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::handicraft;
+    use crate::types::find_handicraft;
+
+    #[test]
+    fn overlays_patch_and_add_handicrafts_in_order() {
+        let dir = env::temp_dir().join(format!("mji-agenda-overlays-{}", std::process::id()));
+        let overlay_dir = dir.join(OVERLAY_DIR_NAME);
+        fs::create_dir_all(&overlay_dir).unwrap();
+        let write = |path: &Path, contents: &str| fs::write(path, contents).unwrap();
+        write(
+            &overlay_dir.join("1-potion.toml"),
+            "[[handicrafts]]\nname = \"IsleworksPotion\"\nvalue = 40\ntime = 6\n",
+        );
+        write(
+            &overlay_dir.join("2-lamp.toml"),
+            r#"
+[[handicrafts]]
+name = "Isleworks Potion"
+value = 50
+
+[[handicrafts]]
+name = "IsleworksOverlayLamp"
+code = 51
+time = 4
+quantity = 1
+value = 30
+category = ["Concoctions"]
+materials = { IslandPalmLeaf = 1 }
+"#,
+        );
+        // not a toml file, so it's left alone
+        write(&overlay_dir.join("notes.txt"), "value = 0");
+        let given = dir.join("given.toml");
+        write(
+            &given,
+            "[[handicrafts]]\nname = \"IsleworksOverlayLamp\"\nvalue = 35\n",
+        );
+        let bad = dir.join("bad.toml");
+        write(&bad, "[rare]\nproduce = []\n");
+
+        let data = load_workshop_data(Some(&dir), &[given]);
+        let rare = load_workshop_data(Some(&dir), &[bad]);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let data = data.unwrap();
+        let find = |id| find_handicraft(&data.handicrafts, &handicraft(id)).unwrap();
+        // later overlays win, and fields they leave out keep the earlier value
+        let potion = find("IsleworksPotion");
+        assert_eq!((potion.value, potion.time, potion.quantity), (50, 6, 1));
+        let lamp = find("IsleworksOverlayLamp");
+        assert_eq!((lamp.value, lamp.time), (35, 4));
+        assert_eq!(
+            data.handicrafts.last().map(|handicraft| handicraft.name),
+            Some(lamp.name)
+        );
+
+        assert!(matches!(
+            rare,
+            Err(WorkshopDataReadError::Overlay { reason, .. }) if reason.contains("not rare")
+        ));
+    }
+}
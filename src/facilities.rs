@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::supply::DAYS_IN_SEASON;
+use crate::types::{MaterialBudget, MaterialName, WorkshopData};
+
+// produce harvested from each plot, on average
+const PRODUCE_PER_PLOT: usize = 5;
+// leavings each animal leaves a day, on average
+const LEAVINGS_PER_ANIMAL: usize = 1;
+// rare materials of each kind found in the expedition's area, on average
+const MATERIALS_PER_EXPEDITION: usize = 5;
+
+fn one() -> usize {
+    1
+}
+
+/// Seeds planted in the cropland
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Crop {
+    pub produce: MaterialName,
+    #[serde(default = "one")]
+    pub plots: usize,
+    /// days from Day 1 until the crop is harvested, so 0 is Day 1 itself
+    pub days_until_collection: usize,
+}
+
+/// Animals in the pasture, whose leavings are collected every day
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Animals {
+    pub leavings: MaterialName,
+    #[serde(default = "one")]
+    pub count: usize,
+}
+
+/// An expedition a granary is sent on
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Expedition {
+    pub area: String,
+    /// days from Day 1 until the expedition comes back, so 0 is Day 1 itself
+    pub days_until_collection: usize,
+}
+
+/// What the cropland, pasture and granaries are set up to bring in over the season
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Facilities {
+    #[serde(default)]
+    pub cropland: Vec<Crop>,
+    #[serde(default)]
+    pub pasture: Vec<Animals>,
+    #[serde(default)]
+    pub granaries: Vec<Expedition>,
+}
+
+#[derive(Debug, Error)]
+pub enum FacilitiesError {
+    #[error("No rare material comes from expeditions to {0}")]
+    UnknownArea(String),
+    #[error("{0} is not produce that grows in the cropland")]
+    UnknownProduce(MaterialName),
+    #[error("{0} is not leavings that animals leave in the pasture")]
+    UnknownLeavings(MaterialName),
+}
+
+impl Facilities {
+    /// Items collected on each day of the season, starting with Day 1. Collections after the
+    /// end of the season are left out
+    pub fn arrivals(
+        &self,
+        data: &WorkshopData,
+    ) -> Result<Vec<HashMap<MaterialName, usize>>, FacilitiesError> {
+        let mut arrivals = vec![HashMap::new(); DAYS_IN_SEASON];
+        let mut collect = |day: usize, material, count| {
+            if let Some(arrivals) = arrivals.get_mut(day) {
+                *arrivals.entry(material).or_default() += count;
+            }
+        };
+        for crop in &self.cropland {
+            if !data
+                .rare
+                .produce
+                .iter()
+                .any(|item| item.name == crop.produce)
+            {
+                return Err(FacilitiesError::UnknownProduce(crop.produce));
+            }
+            collect(
+                crop.days_until_collection,
+                crop.produce,
+                crop.plots * PRODUCE_PER_PLOT,
+            );
+        }
+        for animals in &self.pasture {
+            if !data
+                .rare
+                .leavings
+                .iter()
+                .any(|item| item.name == animals.leavings)
+            {
+                return Err(FacilitiesError::UnknownLeavings(animals.leavings));
+            }
+            for day in 0..DAYS_IN_SEASON {
+                collect(day, animals.leavings, animals.count * LEAVINGS_PER_ANIMAL);
+            }
+        }
+        for expedition in &self.granaries {
+            let materials: Vec<_> = data
+                .rare
+                .material
+                .iter()
+                .filter(|material| material.area == expedition.area)
+                .collect();
+            if materials.is_empty() {
+                return Err(FacilitiesError::UnknownArea(expedition.area.clone()));
+            }
+            for material in materials {
+                collect(
+                    expedition.days_until_collection,
+                    material.name,
+                    MATERIALS_PER_EXPEDITION,
+                );
+            }
+        }
+        Ok(arrivals)
+    }
+
+    /// Budgets that will be on hand on each day of the season if nothing is used, starting from
+    /// what's on hand before anything is collected on Day 1
+    pub fn projected_budgets(
+        &self,
+        data: &WorkshopData,
+        on_hand: &MaterialBudget,
+    ) -> Result<Vec<MaterialBudget>, FacilitiesError> {
+        let mut budget = on_hand.clone();
+        Ok(self
+            .arrivals(data)?
+            .iter()
+            .map(|arrivals| {
+                add_arrivals(&mut budget, arrivals);
+                budget.clone()
+            })
+            .collect())
+    }
+}
+
+/// Adds what was collected to the budget, ignoring anything that can't run out
+pub fn add_arrivals(budget: &mut MaterialBudget, arrivals: &HashMap<MaterialName, usize>) {
+    for (mat, count) in budget.iter_mut() {
+        *count += arrivals.get(mat).copied().unwrap_or_default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, material};
+
+    #[test]
+    fn facilities_bring_items_in_on_their_day() {
+        let data = testing::data();
+        let (popoto, milk, garnet) = (
+            material("IslandPopoto"),
+            material("SanctuaryMilk"),
+            material("RawIslandGarnet"),
+        );
+        let facilities = Facilities {
+            cropland: vec![
+                Crop {
+                    produce: popoto,
+                    plots: 2,
+                    days_until_collection: 3,
+                },
+                // collected after the season ends
+                Crop {
+                    produce: popoto,
+                    plots: 1,
+                    days_until_collection: DAYS_IN_SEASON,
+                },
+            ],
+            pasture: vec![Animals {
+                leavings: milk,
+                count: 2,
+            }],
+            granaries: vec![Expedition {
+                area: "FatalFalls".to_string(),
+                days_until_collection: 1,
+            }],
+        };
+
+        let arrivals = facilities.arrivals(data).unwrap();
+        assert_eq!(arrivals.len(), DAYS_IN_SEASON);
+        assert_eq!(arrivals[0], HashMap::from([(milk, 2)]));
+        assert_eq!(arrivals[1], HashMap::from([(milk, 2), (garnet, 5)]));
+        assert_eq!(arrivals[3], HashMap::from([(milk, 2), (popoto, 10)]));
+        assert_eq!(arrivals[6], HashMap::from([(milk, 2)]));
+
+        let on_hand = MaterialBudget::from([(milk, 1), (popoto, 0), (garnet, 0)]);
+        let budgets = facilities.projected_budgets(data, &on_hand).unwrap();
+        assert_eq!(
+            budgets[0],
+            MaterialBudget::from([(milk, 3), (popoto, 0), (garnet, 0)])
+        );
+        assert_eq!(
+            budgets[6],
+            MaterialBudget::from([(milk, 15), (popoto, 10), (garnet, 5)])
+        );
+
+        let lost = Facilities {
+            granaries: vec![Expedition {
+                area: "Nowhere".to_string(),
+                days_until_collection: 0,
+            }],
+            ..Facilities::default()
+        };
+        assert!(matches!(
+            lost.arrivals(data),
+            Err(FacilitiesError::UnknownArea(area)) if area == "Nowhere"
+        ));
+        let milk_crop = Facilities {
+            cropland: vec![Crop {
+                produce: milk,
+                plots: 1,
+                days_until_collection: 0,
+            }],
+            ..Facilities::default()
+        };
+        assert!(matches!(
+            milk_crop.arrivals(data),
+            Err(FacilitiesError::UnknownProduce(produce)) if produce == milk
+        ));
+        let popoto_animals = Facilities {
+            pasture: vec![Animals {
+                leavings: popoto,
+                count: 1,
+            }],
+            ..Facilities::default()
+        };
+        assert!(matches!(
+            popoto_animals.arrivals(data),
+            Err(FacilitiesError::UnknownLeavings(leavings)) if leavings == popoto
+        ));
+    }
+}
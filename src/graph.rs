@@ -0,0 +1,174 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt::Debug,
+    hash::Hash,
+};
+
+use clap::{Args, ValueEnum};
+use mji_agenda::{
+    create_handicraft_graph, create_material_graph, find_handicraft, CategoryName, Handicraft,
+    HandicraftGraphNode, HandicraftName, MaterialGraphNode, UnknownNameError,
+};
+use petgraph::{
+    dot::{Config, Dot},
+    graphmap::{GraphMap, NodeTrait},
+    Directed,
+};
+
+// X11 colours light enough to read labels on, one per category
+const PALETTE: [&str; 16] = [
+    "lightblue",
+    "lightcoral",
+    "lightgoldenrod",
+    "palegreen",
+    "plum",
+    "lightsalmon",
+    "khaki",
+    "lightcyan",
+    "pink",
+    "wheat",
+    "thistle",
+    "aquamarine",
+    "lightsteelblue",
+    "peachpuff",
+    "lavender",
+    "darkseagreen",
+];
+const HIGHLIGHT: &str = "color = red, penwidth = 3";
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GraphKind {
+    /// Handicrafts linked to their categories
+    Category,
+    /// Handicrafts linked to their materials
+    Material,
+}
+
+#[derive(Args)]
+pub struct GraphArgs {
+    /// Which graph to print
+    #[arg(long, value_enum, default_value_t = GraphKind::Category)]
+    kind: GraphKind,
+    /// Agenda to highlight, as handicrafts separated by commas, e.g.
+    /// "Isleworks Potion,Isleworks Firesand"
+    #[arg(long, value_delimiter = ',')]
+    highlight: Vec<String>,
+    /// Fill handicrafts with the colour of their first category, and categories with their own
+    #[arg(long)]
+    colour: bool,
+}
+
+pub fn run(handicrafts: &[Handicraft], args: GraphArgs) -> Result<(), UnknownNameError> {
+    let agenda = args
+        .highlight
+        .iter()
+        .map(|name| name.trim().parse())
+        .collect::<Result<Vec<HandicraftName>, _>>()?;
+    let categories: BTreeSet<_> = handicrafts
+        .iter()
+        .flat_map(|handicraft| handicraft.category.iter().copied())
+        .collect();
+    let colours: HashMap<_, _> = categories.into_iter().zip(PALETTE.iter().cycle()).collect();
+    let fill = |category: Option<&CategoryName>| {
+        category
+            .filter(|_| args.colour)
+            .map(|category| format!(", style = filled, fillcolor = {}", colours[category]))
+            .unwrap_or_default()
+    };
+    let first_category = |name: HandicraftName| {
+        find_handicraft(handicrafts, &name).and_then(|handicraft| handicraft.category.first())
+    };
+    let highlighted_nodes: HashSet<_> = agenda.iter().copied().collect();
+
+    match args.kind {
+        GraphKind::Category => {
+            let (_, graph) = create_handicraft_graph(handicrafts.iter());
+            // consecutive steps are linked through every category they share
+            let mut highlighted_edges = HashSet::new();
+            for step in agenda.windows(2) {
+                let categories = |name| {
+                    graph
+                        .neighbors(HandicraftGraphNode::Handicraft(name))
+                        .collect::<HashSet<_>>()
+                };
+                for category in categories(step[0]).intersection(&categories(step[1])) {
+                    highlighted_edges.insert((HandicraftGraphNode::Handicraft(step[0]), *category));
+                    highlighted_edges.insert((HandicraftGraphNode::Handicraft(step[1]), *category));
+                }
+            }
+            print_dot(
+                &graph,
+                |node| match node {
+                    HandicraftGraphNode::Handicraft(name) => {
+                        let highlight = if highlighted_nodes.contains(&name) {
+                            format!(", {}", HIGHLIGHT)
+                        } else {
+                            String::new()
+                        };
+                        format!(
+                            "label = \"{}\"{}{}",
+                            name,
+                            fill(first_category(name)),
+                            highlight
+                        )
+                    }
+                    HandicraftGraphNode::Category(name) => {
+                        format!("label = \"{}\", shape = box{}", name, fill(Some(&name)))
+                    }
+                },
+                |edge| highlighted_edges.contains(&edge),
+            );
+        }
+        GraphKind::Material => {
+            let (_, graph) = create_material_graph(handicrafts.iter());
+            print_dot(
+                &graph,
+                |node| match node {
+                    MaterialGraphNode::Handicraft(name) => {
+                        let highlight = if highlighted_nodes.contains(&name) {
+                            format!(", {}", HIGHLIGHT)
+                        } else {
+                            String::new()
+                        };
+                        format!(
+                            "label = \"{}\"{}{}",
+                            name,
+                            fill(first_category(name)),
+                            highlight
+                        )
+                    }
+                    MaterialGraphNode::Material(name) => {
+                        format!("label = \"{}\", shape = box", name)
+                    }
+                },
+                // a highlighted handicraft's materials are all on the path
+                |(from, _)| highlighted_nodes.contains(&from.unwrap_handicraft()),
+            );
+        }
+    }
+    Ok(())
+}
+
+fn print_dot<N>(
+    graph: &GraphMap<N, u8, Directed>,
+    node_attrs: impl Fn(N) -> String,
+    highlighted: impl Fn((N, N)) -> bool,
+) where
+    N: NodeTrait + Debug + Hash,
+{
+    println!(
+        "{:?}",
+        Dot::with_attr_getters(
+            graph,
+            &[Config::EdgeNoLabel, Config::NodeNoLabel],
+            &|_, (from, to, _)| {
+                if highlighted((from, to)) {
+                    HIGHLIGHT.to_string()
+                } else {
+                    String::new()
+                }
+            },
+            &|_, (node, _)| node_attrs(node),
+        )
+    );
+}
@@ -0,0 +1,218 @@
+use std::{collections::HashMap, fs, io, path::Path};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::agenda::unlocked_handicrafts;
+use crate::facilities::{Facilities, FacilitiesError};
+use crate::peak::PeakError;
+use crate::supply::SupplyState;
+use crate::types::{
+    material_budget, DemandObservation, DemandShift, Handicraft, HandicraftName, MaterialBudget,
+    MaterialName, Peak, PopSupply, Popularity, PopularityTableError, RareItemCount, Supply,
+    WorkshopData, WorkshopRank,
+};
+
+/// Everything needed to plan a day, read from a TOML or JSON file
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct PlanInput {
+    /// only handicrafts unlocked by this island rank are planned with, all of them if not given
+    pub island_rank: Option<usize>,
+    pub workshop_rank: Option<WorkshopRank>,
+    pub groove: Option<usize>,
+    pub groove_cap: Option<usize>,
+    pub workshops: Option<usize>,
+    /// week to take popularity from, instead of giving it for every handicraft
+    pub week: Option<usize>,
+    /// date to take popularity for, counting weeks from the tables' reference date
+    pub date: Option<String>,
+    #[serde(default)]
+    pub popularity: HashMap<HandicraftName, Popularity>,
+    #[serde(default)]
+    pub supply: HashMap<HandicraftName, Supply>,
+    /// when each handicraft's supply bottoms out, for planning a week. Any left out have no peak
+    #[serde(default)]
+    pub peaks: HashMap<HandicraftName, Peak>,
+    /// what the workshop showed on each day of the season so far, starting with Day 1, for
+    /// predicting peaks when none are given
+    #[serde(default)]
+    pub history: Vec<HashMap<HandicraftName, ObservedDemand>>,
+    /// rare items on hand, any that are left out count as none
+    #[serde(default)]
+    pub rare: HashMap<MaterialName, usize>,
+    /// common materials on hand, which are only kept track of when listed here
+    #[serde(default)]
+    pub inventory: HashMap<MaterialName, usize>,
+    /// what the cropland, pasture and granaries bring in
+    #[serde(default)]
+    pub facilities: Facilities,
+}
+
+/// Supply and the demand shift arrow shown for a handicraft on one day
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObservedDemand {
+    pub supply: Supply,
+    pub demand_shift: DemandShift,
+}
+
+#[derive(Debug, Error)]
+pub enum PlanInputError {
+    #[error("Could not read input file: {0}")]
+    Io(#[from] io::Error),
+    #[error("Could not parse input file as toml: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("Could not parse input file as json: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("No popularity given for {0}")]
+    MissingPopularity(HandicraftName),
+    #[error("No supply given for {0}")]
+    MissingSupply(HandicraftName),
+    #[error(transparent)]
+    PopularityTable(#[from] PopularityTableError),
+    #[error(transparent)]
+    Facilities(#[from] FacilitiesError),
+    #[error(transparent)]
+    Peak(#[from] PeakError),
+}
+
+impl PlanInput {
+    /// Reads a json file if the path ends in .json, otherwise toml
+    pub fn from_path(path: &Path) -> Result<Self, PlanInputError> {
+        let raw = fs::read_to_string(path)?;
+        if path.extension().is_some_and(|ext| ext == "json") {
+            Ok(serde_json::from_str(&raw)?)
+        } else {
+            // going through a value lets handicraft and material names be used as keys
+            Ok(raw.parse::<toml::Value>()?.try_into()?)
+        }
+    }
+
+    /// Handicrafts unlocked at the island rank
+    pub fn handicrafts(&self, data: &WorkshopData) -> Vec<Handicraft> {
+        unlocked_handicrafts(&data.handicrafts, self.island_rank.unwrap_or(usize::MAX))
+            .cloned()
+            .collect()
+    }
+
+    /// Popularity of every unlocked handicraft, from the input or else the week's table
+    pub fn popularity(
+        &self,
+        data: &WorkshopData,
+    ) -> Result<HashMap<HandicraftName, Popularity>, PlanInputError> {
+        let week = match (self.week, &self.date) {
+            (Some(week), _) => Some(week),
+            (None, Some(date)) => Some(data.popularity.week_for_date(date)?),
+            (None, None) => None,
+        };
+        let table = week
+            .map(|week| data.popularity.table_for_week(week))
+            .transpose()?
+            .unwrap_or_default();
+        self.handicrafts(data)
+            .iter()
+            .map(|handicraft| {
+                let popularity = self
+                    .popularity
+                    .get(&handicraft.name)
+                    .or_else(|| table.get(&handicraft.name))
+                    .ok_or(PlanInputError::MissingPopularity(handicraft.name))?;
+                Ok((handicraft.name, *popularity))
+            })
+            .collect()
+    }
+
+    pub fn pop_supplies(
+        &self,
+        data: &WorkshopData,
+    ) -> Result<HashMap<HandicraftName, PopSupply>, PlanInputError> {
+        let popularity = self.popularity(data)?;
+        self.handicrafts(data)
+            .iter()
+            .map(|handicraft| {
+                let supply = self
+                    .supply
+                    .get(&handicraft.name)
+                    .ok_or(PlanInputError::MissingSupply(handicraft.name))?;
+                Ok((
+                    handicraft.name,
+                    PopSupply {
+                        popularity: popularity[&handicraft.name],
+                        supply: *supply,
+                    },
+                ))
+            })
+            .collect()
+    }
+
+    /// How supply plays out over the season, from the Day 1 supply and the given peaks. Without
+    /// any peaks but with a history, Day 1 supply comes from the history and peaks are predicted
+    /// from it
+    pub fn supply_state(&self, data: &WorkshopData) -> Result<SupplyState, PlanInputError> {
+        let handicrafts = self.handicrafts(data);
+        let Some(day_one) = self.history.first().filter(|_| self.peaks.is_empty()) else {
+            let day_one = handicrafts
+                .iter()
+                .map(|handicraft| {
+                    let supply = self
+                        .supply
+                        .get(&handicraft.name)
+                        .ok_or(PlanInputError::MissingSupply(handicraft.name))?;
+                    Ok((handicraft.name, *supply))
+                })
+                .collect::<Result<_, PlanInputError>>()?;
+            return Ok(SupplyState::new(day_one, self.peaks.clone(), data.supply));
+        };
+        if let Some(handicraft) = handicrafts
+            .iter()
+            .find(|handicraft| !day_one.contains_key(&handicraft.name))
+        {
+            return Err(PlanInputError::MissingSupply(handicraft.name));
+        }
+        // only the supply and demand shift matter for peaks, so the popularity is this week's
+        let popularity = self.popularity(data)?;
+        let history: Vec<_> = self
+            .history
+            .iter()
+            .map(|day| {
+                day.iter()
+                    .filter_map(|(name, seen)| {
+                        let observation = DemandObservation {
+                            pop_supply: PopSupply {
+                                popularity: *popularity.get(name)?,
+                                supply: seen.supply,
+                            },
+                            demand_shift: seen.demand_shift,
+                        };
+                        Some((*name, observation))
+                    })
+                    .collect()
+            })
+            .collect();
+        Ok(SupplyState::from_history(&history, data.supply)?)
+    }
+
+    pub fn rare_item_counts(&self, data: &WorkshopData) -> Vec<RareItemCount> {
+        data.rare_items()
+            .map(|rare| RareItemCount {
+                count: self.rare.get(rare.name()).copied().unwrap_or_default(),
+                rare,
+            })
+            .collect()
+    }
+
+    /// Rare items and inventory on hand, before the facilities bring anything in
+    pub fn material_budget(&self, data: &WorkshopData) -> MaterialBudget {
+        material_budget(&self.rare_item_counts(data), &self.inventory)
+    }
+
+    /// What's on hand at the start of each day of the season, starting with Day 1, counting what
+    /// the facilities bring in that day and assuming nothing is used
+    pub fn projected_budgets(
+        &self,
+        data: &WorkshopData,
+    ) -> Result<Vec<MaterialBudget>, PlanInputError> {
+        Ok(self
+            .facilities
+            .projected_budgets(data, &self.material_budget(data))?)
+    }
+}
@@ -0,0 +1,206 @@
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+};
+
+use mji_agenda::{
+    material_budget, unlocked_handicrafts, Groove, Handicraft, HandicraftName, PopSupply,
+    Popularity, PopularityTables, PricingModifiers, RareItemCount, RareItemVariant, WorkshopData,
+};
+
+use crate::{solve, OutputFormat, DEFAULT_TOP};
+
+pub fn run(data: &WorkshopData) {
+    println!("Input amount of rare items in Isleventory");
+
+    let mut stdin = io::stdin();
+    let mut input_buf = String::new();
+    let rare_item_counts: Vec<_> = data
+        .rare_items()
+        .map(|item| input_rare_item_count(&stdin, &mut input_buf, item))
+        .collect();
+    println!("rare_item_counts: {:?}", rare_item_counts);
+
+    let island_rank = input_island_rank(&stdin, &mut input_buf);
+    let handicrafts: Vec<_> = unlocked_handicrafts(&data.handicrafts, island_rank)
+        .cloned()
+        .collect();
+
+    let popularity = input_popularity_week(&stdin, &mut input_buf, &data.popularity);
+
+    println!("Input popularity (L = Low, A = Average, H = High, V = Very High) and supply (N = Nonexistent, I = Insufficient, S = Sufficient, U = Surplus) for products");
+    let handicraft_pop_supply: HashMap<_, _> = handicrafts
+        .iter()
+        .map(|item| {
+            (
+                item.name,
+                input_product_pop_supply(
+                    &mut stdin,
+                    &mut input_buf,
+                    item,
+                    popularity
+                        .as_ref()
+                        .and_then(|popularity| popularity.get(&item.name).copied()),
+                ),
+            )
+        })
+        .collect();
+    println!("handicraft_pop_supply: {:?}", handicraft_pop_supply);
+
+    let modifiers = input_pricing_modifiers(&stdin, &mut input_buf);
+    println!("modifiers: {:?}", modifiers);
+
+    let (workshop_count, groove_cap) = input_day_settings(&stdin, &mut input_buf);
+
+    solve(
+        data,
+        &handicrafts,
+        handicraft_pop_supply,
+        material_budget(&rare_item_counts, &HashMap::new()),
+        modifiers.workshop_rank,
+        Groove::new(modifiers.groove, groove_cap),
+        workshop_count,
+        DEFAULT_TOP,
+        OutputFormat::Text,
+    );
+}
+
+fn input_rare_item_count(
+    stdin: &io::Stdin,
+    input_buf: &mut String,
+    rare: RareItemVariant,
+) -> RareItemCount {
+    print!("{}: ", rare.name());
+    io::stdout().flush().unwrap();
+    stdin
+        .read_line(input_buf)
+        .expect("Tried reading user input for rare item count");
+    let count = input_buf
+        .trim()
+        .parse()
+        .expect("Must be an unsigned integer");
+    input_buf.clear();
+    RareItemCount { rare, count }
+}
+
+fn input_island_rank(stdin: &io::Stdin, input_buf: &mut String) -> usize {
+    print!("Island rank (blank to use every product): ");
+    io::stdout().flush().unwrap();
+    stdin
+        .read_line(input_buf)
+        .expect("Tried reading user input for island rank");
+    let rank = input_buf.trim();
+    let island_rank = if rank.is_empty() {
+        usize::MAX
+    } else {
+        rank.parse().expect("Must be an unsigned integer")
+    };
+    input_buf.clear();
+    island_rank
+}
+
+fn input_popularity_week(
+    stdin: &io::Stdin,
+    input_buf: &mut String,
+    tables: &PopularityTables,
+) -> Option<HashMap<HandicraftName, Popularity>> {
+    print!(
+        "Popularity week or date, e.g. 2022-08-23 (blank to input popularity for each product): "
+    );
+    io::stdout().flush().unwrap();
+    stdin
+        .read_line(input_buf)
+        .expect("Tried reading user input for popularity week");
+    let week = input_buf.trim();
+    let popularity = (!week.is_empty()).then(|| {
+        let week = if week.contains('-') {
+            tables
+                .week_for_date(week)
+                .expect("Could not find the week for that date")
+        } else {
+            week.parse().expect("Must be an unsigned integer")
+        };
+        tables
+            .table_for_week(week)
+            .expect("Could not find popularity table for week")
+    });
+    input_buf.clear();
+    popularity
+}
+
+fn input_product_pop_supply(
+    stdin: &mut io::Stdin,
+    input_buf: &mut String,
+    handicraft: &Handicraft,
+    popularity: Option<Popularity>,
+) -> PopSupply {
+    let popularity = popularity.unwrap_or_else(|| {
+        print!("{} popularity: ", handicraft.name);
+        io::stdout().flush().unwrap();
+        stdin
+            .read_line(input_buf)
+            .expect("Tried reading user input for product popularity");
+        let popularity = input_buf.trim().parse().expect("Must be a valid character");
+        input_buf.clear();
+        popularity
+    });
+    print!("{} supply: ", handicraft.name);
+    io::stdout().flush().unwrap();
+    stdin
+        .read_line(input_buf)
+        .expect("Tried reading user input for product supply");
+    let supply = input_buf.trim().parse().expect("Must be a valid character");
+    input_buf.clear();
+    PopSupply { popularity, supply }
+}
+
+fn input_pricing_modifiers(stdin: &io::Stdin, input_buf: &mut String) -> PricingModifiers {
+    print!("Workshop rank (1-5): ");
+    io::stdout().flush().unwrap();
+    stdin
+        .read_line(input_buf)
+        .expect("Tried reading user input for workshop rank");
+    let workshop_rank = input_buf
+        .trim()
+        .parse()
+        .expect("Must be a rank from 1 to 5");
+    input_buf.clear();
+    print!("Current groove: ");
+    io::stdout().flush().unwrap();
+    stdin
+        .read_line(input_buf)
+        .expect("Tried reading user input for groove");
+    let groove = input_buf
+        .trim()
+        .parse()
+        .expect("Must be an unsigned integer");
+    input_buf.clear();
+    PricingModifiers {
+        workshop_rank,
+        groove,
+    }
+}
+
+fn input_day_settings(stdin: &io::Stdin, input_buf: &mut String) -> (usize, usize) {
+    print!("Number of workshops: ");
+    io::stdout().flush().unwrap();
+    stdin
+        .read_line(input_buf)
+        .expect("Tried reading user input for number of workshops");
+    let workshop_count = input_buf
+        .trim()
+        .parse()
+        .expect("Must be an unsigned integer");
+    input_buf.clear();
+    print!("Groove cap: ");
+    io::stdout().flush().unwrap();
+    stdin
+        .read_line(input_buf)
+        .expect("Tried reading user input for groove cap");
+    let groove_cap = input_buf
+        .trim()
+        .parse()
+        .expect("Must be an unsigned integer");
+    input_buf.clear();
+    (workshop_count, groove_cap)
+}
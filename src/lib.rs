@@ -1,5 +1,28 @@
 mod agenda;
+mod data;
+mod facilities;
+mod input;
+mod name;
+mod peak;
+mod plan;
+mod report;
+mod share;
+mod shopping;
+mod supply;
+#[cfg(test)]
+mod testing;
 mod types;
+mod validate;
 
 pub use crate::agenda::*;
+pub use crate::data::*;
+pub use crate::facilities::*;
+pub use crate::input::*;
+pub use crate::peak::*;
+pub use crate::plan::*;
+pub use crate::report::*;
+pub use crate::share::*;
+pub use crate::shopping::*;
+pub use crate::supply::*;
 pub use crate::types::*;
+pub use crate::validate::*;
@@ -1,121 +1,550 @@
-use std::io::{self, Write};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
 
-use mji_agenda::{create_handicraft_graph, find_agendas, RareItemVariant, WorkshopData};
-use mji_agenda::{Handicraft, PopSupply, RareItemCount};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use graph::GraphArgs;
+use mji_agenda::{
+    create_handicraft_graph, evaluate, export_code, export_csv, find_agendas, import_code,
+    import_csv, load_workshop_data, material_report, plan_day, plan_week, price_schedule,
+    shopping_entries, shopping_list, AgendaReport, DayReport, Groove, PlanInput, SolveReport,
+    WeekReport, DAY_PLAN_AGENDAS,
+};
+use mji_agenda::{
+    Agenda, DayPlan, Handicraft, HandicraftName, MaterialBudget, MaterialUse, PopSupply,
+    Popularity, PricingModifiers, ShoppingList, Supply, WeekPlan, WorkshopData, WorkshopRank,
+};
+use serde::Serialize;
 
-fn main() {
-    let raw = include_bytes!("handicrafts.toml");
-    // let raw = fs::read_to_string("src/handicrafts.toml").unwrap();
-    let data: WorkshopData = String::from_utf8_lossy(raw)
-        .parse::<toml::Value>()
-        .unwrap()
-        .try_into()
-        .unwrap();
+mod graph;
+mod interactive;
 
-    // useful for mapping material connections, less useful for making agendas with efficiency bonus
-    // let (recipe_nodes, handicraft_graph) = create_material_graph(data.handicrafts.iter());
-    let (recipe_nodes, handicraft_graph) = create_handicraft_graph(data.handicrafts.iter());
-
-    println!("Input amount of rare items in Isleventory");
-
-    let mut stdin = io::stdin();
-    let mut input_buf = String::new();
-    let rare_item_counts: Vec<_> = data
-        .rare
-        .material
-        .into_iter()
-        .map(RareItemVariant::WithArea)
-        .chain(data.rare.produce.into_iter().map(RareItemVariant::RareItem))
-        .chain(
-            data.rare
-                .leavings
-                .into_iter()
-                .map(RareItemVariant::RareItem),
-        )
-        .map(|item| input_rare_item_count(&stdin, &mut input_buf, item))
-        .collect();
-    println!("rare_item_counts: {:?}", rare_item_counts);
+// the highest groove any island can reach
+const DEFAULT_GROOVE_CAP: usize = 35;
+const DEFAULT_WORKSHOPS: usize = 3;
+const DEFAULT_TOP: usize = 5;
+
+/// Generate optimal agendas for the island sanctuary workshop
+#[derive(Parser)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+    /// Workshop data file, or a directory holding one and an overlays directory. Defaults to
+    /// $MJI_AGENDA_DATA, then the mji-agenda config directory, then the built in data
+    #[arg(long, global = true)]
+    data: Option<PathBuf>,
+    /// File patching handicrafts in the workshop data, applied in the order given
+    #[arg(long, global = true)]
+    overlay: Vec<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Find the best agendas and plan a day across every workshop
+    Solve(SolveArgs),
+    /// Plan every day of the season, choosing which two to rest
+    Week(WeekArgs),
+    /// Price an agenda chosen by hand, checking that it can be worked
+    Evaluate(EvaluateArgs),
+    /// Answer a prompt for every input instead of passing them in
+    Interactive,
+    /// List every handicraft in the workshop data
+    ListHandicrafts,
+    /// Print the handicraft category graph, or the material graph, in DOT format
+    Graph(GraphArgs),
+    /// Check that the workshop data loads
+    ValidateData,
+}
+
+/// Flags override whatever the input file gives
+#[derive(Args)]
+struct InputArgs {
+    /// TOML or JSON file with popularity, supply, rare item counts and modifiers
+    #[arg(short, long)]
+    input: Option<PathBuf>,
+    /// Island sanctuary rank, leaving out handicrafts that haven't unlocked yet [default: all]
+    #[arg(long)]
+    island_rank: Option<usize>,
+    /// Workshop rank, from 1 to 5 [default: 1]
+    #[arg(long)]
+    workshop_rank: Option<WorkshopRank>,
+    /// Groove at the start of the day [default: 0]
+    #[arg(long)]
+    groove: Option<usize>,
+    /// Highest groove the island can reach [default: 35]
+    #[arg(long)]
+    groove_cap: Option<usize>,
+    /// Number of workshops [default: 3]
+    #[arg(long)]
+    workshops: Option<usize>,
+    /// Week to take popularity from
+    #[arg(long)]
+    week: Option<usize>,
+    /// Date to take popularity for, e.g. 2022-08-23, when no week is given
+    #[arg(long)]
+    date: Option<String>,
+    /// Popularity of a handicraft (L, A, H or V), e.g. "Isleworks Potion=H"
+    #[arg(long, value_parser = parse_assignment::<String, Popularity>)]
+    popularity: Vec<(String, Popularity)>,
+    /// Supply of a handicraft (N, I, S, U or O), e.g. "Isleworks Potion=S"
+    #[arg(long, value_parser = parse_assignment::<String, Supply>)]
+    supply: Vec<(String, Supply)>,
+    /// Count of a rare item on hand, e.g. "Sanctuary Milk=3"
+    #[arg(long, value_parser = parse_assignment::<String, usize>)]
+    rare: Vec<(String, usize)>,
+}
+
+#[derive(Args)]
+struct SolveArgs {
+    #[command(flatten)]
+    input: InputArgs,
+    /// How many of the best single workshop agendas to print
+    #[arg(short = 'n', long, default_value_t = DEFAULT_TOP)]
+    top: usize,
+    /// How to print the results
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+    /// CSV file or share code of someone else's schedule, to price against this input instead of
+    /// searching for the best plan
+    #[arg(long)]
+    schedule: Option<String>,
+}
+
+#[derive(Args)]
+struct WeekArgs {
+    #[command(flatten)]
+    input: InputArgs,
+    /// How to print the plan
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+}
 
-    println!("Input popularity (L = Low, A = Average, H = High, V = Very High) and supply (N = Nonexistent, I = Insufficient, S = Sufficient, U = Surplus) for products");
-    let handicraft_pop_supply = data
+#[derive(Args)]
+struct EvaluateArgs {
+    /// Handicrafts in the order they're made, separated by commas, e.g.
+    /// "Isleworks Potion,Isleworks Firesand"
+    #[arg(value_delimiter = ',', required = true)]
+    handicrafts: Vec<String>,
+    #[command(flatten)]
+    input: InputArgs,
+    /// How to print the agenda. JSON has the same fields as an agenda from `solve --format json`
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Readable text
+    Text,
+    /// JSON for other tools to read, with stable field names
+    Json,
+    /// CSV of the plan with one row per workshop slot, for spreadsheets
+    Csv,
+    /// Short share code of the plan
+    Code,
+}
+
+fn parse_assignment<K, V>(s: &str) -> Result<(K, V), String>
+where
+    K: std::str::FromStr,
+    K::Err: std::fmt::Display,
+    V: std::str::FromStr,
+    V::Err: std::fmt::Display,
+{
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("Expected NAME=VALUE, got {}", s))?;
+    Ok((
+        key.trim().parse().map_err(|e: K::Err| e.to_string())?,
+        value.trim().parse().map_err(|e: V::Err| e.to_string())?,
+    ))
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let data = match load_workshop_data(cli.data.as_deref(), &cli.overlay) {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match cli.command {
+        Command::Solve(args) => {
+            if let Err(e) = solve_with_args(&data, args) {
+                eprintln!("{}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+        Command::Week(args) => {
+            if let Err(e) = week_with_args(&data, args) {
+                eprintln!("{}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+        Command::Evaluate(args) => {
+            if let Err(e) = evaluate_with_args(&data, args) {
+                eprintln!("{}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+        Command::Interactive => interactive::run(&data),
+        Command::ListHandicrafts => {
+            for handicraft in &data.handicrafts {
+                println!(
+                    "{}: rank {}, {}h, {} cowries, {}",
+                    handicraft.name,
+                    handicraft.rank,
+                    handicraft.time,
+                    handicraft.value,
+                    handicraft
+                        .category
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            }
+        }
+        Command::Graph(args) => {
+            if let Err(e) = graph::run(&data.handicrafts, args) {
+                eprintln!("{}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+        Command::ValidateData => println!(
+            "Loaded {} handicrafts and {} rare items",
+            data.handicrafts.len(),
+            data.rare_items().count()
+        ),
+    }
+    ExitCode::SUCCESS
+}
+
+fn plan_input(args: InputArgs) -> anyhow::Result<PlanInput> {
+    let mut input = match &args.input {
+        Some(path) => PlanInput::from_path(path)?,
+        None => PlanInput::default(),
+    };
+    input.island_rank = args.island_rank.or(input.island_rank);
+    input.workshop_rank = args.workshop_rank.or(input.workshop_rank);
+    input.groove = args.groove.or(input.groove);
+    input.groove_cap = args.groove_cap.or(input.groove_cap);
+    input.workshops = args.workshops.or(input.workshops);
+    input.week = args.week.or(input.week);
+    input.date = args.date.or(input.date);
+    // names can only be looked up once the workshop data is loaded
+    for (name, popularity) in args.popularity {
+        input.popularity.insert(name.parse()?, popularity);
+    }
+    for (name, supply) in args.supply {
+        input.supply.insert(name.parse()?, supply);
+    }
+    for (name, count) in args.rare {
+        input.rare.insert(name.parse()?, count);
+    }
+    Ok(input)
+}
+
+fn solve_with_args(data: &WorkshopData, args: SolveArgs) -> anyhow::Result<()> {
+    let input = plan_input(args.input)?;
+    if let Some(shared) = &args.schedule {
+        return price_imported(data, &input, shared, args.format);
+    }
+
+    // the day is planned with whatever the facilities bring in on Day 1
+    let budget = input.projected_budgets(data)?.swap_remove(0);
+    solve(
+        data,
+        &input.handicrafts(data),
+        input.pop_supplies(data)?,
+        budget,
+        input.workshop_rank.unwrap_or_default(),
+        starting_groove(&input),
+        input.workshops.unwrap_or(DEFAULT_WORKSHOPS),
+        args.top,
+        args.format,
+    );
+    Ok(())
+}
+
+fn week_with_args(data: &WorkshopData, args: WeekArgs) -> anyhow::Result<()> {
+    let input = plan_input(args.input)?;
+    let handicrafts = input.handicrafts(data);
+    let week = plan_week(
+        &handicrafts,
+        &input.popularity(data)?,
+        &input.supply_state(data)?,
+        input.material_budget(data),
+        &input.facilities.arrivals(data)?,
+        input.workshops.unwrap_or(DEFAULT_WORKSHOPS),
+        input.workshop_rank.unwrap_or_default(),
+        starting_groove(&input),
+    );
+    print_week(data, &input, &handicrafts, &week, args.format)
+}
+
+fn evaluate_with_args(data: &WorkshopData, args: EvaluateArgs) -> anyhow::Result<()> {
+    let agenda = args
         .handicrafts
         .iter()
-        .map(|item| {
-            (
-                item.name,
-                input_product_pop_supply(&mut stdin, &mut input_buf, item),
-            )
-        })
-        .collect();
-    println!("handicraft_pop_supply: {:?}", handicraft_pop_supply);
+        .map(|name| name.trim().parse())
+        .collect::<Result<Vec<HandicraftName>, _>>()?;
+    let input = plan_input(args.input)?;
+    let handicrafts = input.handicrafts(data);
+    let day = evaluate(
+        &handicrafts,
+        &agenda,
+        &input.pop_supplies(data)?,
+        input.workshop_rank.unwrap_or_default(),
+        starting_groove(&input),
+    )?;
+    match args.format {
+        OutputFormat::Text => {
+            print_agenda(&day.agendas[0]);
+            println!("groove after: {}", day.groove.value);
+        }
+        OutputFormat::Json => print_json(&AgendaReport::new(&handicrafts, &day.agendas[0])),
+        OutputFormat::Csv | OutputFormat::Code => {
+            print_shared(&handicrafts, &[DayPlan::Work(day)], args.format)
+        }
+    }
+    Ok(())
+}
 
+// groove from the input, kept within its cap so agendas and the day plan are priced alike
+fn starting_groove(input: &PlanInput) -> Groove {
+    Groove::new(
+        input.groove.unwrap_or_default(),
+        input.groove_cap.unwrap_or(DEFAULT_GROOVE_CAP),
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn solve(
+    data: &WorkshopData,
+    handicrafts: &[Handicraft],
+    handicraft_pop_supply: HashMap<HandicraftName, PopSupply>,
+    budget: MaterialBudget,
+    workshop_rank: WorkshopRank,
+    groove: Groove,
+    workshop_count: usize,
+    top: usize,
+    format: OutputFormat,
+) {
+    let modifiers = PricingModifiers {
+        workshop_rank,
+        groove: groove.value,
+    };
+    // useful for mapping material connections, less useful for making agendas with efficiency bonus
+    // let (recipe_nodes, handicraft_graph) = create_material_graph(data.handicrafts.iter());
+    let (recipe_nodes, handicraft_graph) = create_handicraft_graph(handicrafts.iter());
+    let handicraft_pricing_info: HashMap<_, _> = handicrafts
+        .iter()
+        .map(|handicraft| (handicraft.name, handicraft.as_pricing_info()))
+        .collect();
     let agendas = find_agendas(
-        &data.handicrafts,
-        handicraft_pop_supply,
-        rare_item_counts,
+        handicrafts,
+        handicraft_pop_supply.clone(),
+        budget.clone(),
         recipe_nodes,
         handicraft_graph,
-        data.handicrafts
-            .iter()
-            .map(|handicraft| (handicraft.name, handicraft.as_pricing_info()))
-            .collect(),
+        handicraft_pricing_info.clone(),
+        modifiers,
+        top.max(DAY_PLAN_AGENDAS),
+    );
+
+    let day = plan_day(
+        handicrafts,
+        agendas.clone(),
+        workshop_count,
+        &budget,
+        &handicraft_pop_supply,
+        &handicraft_pricing_info,
+        workshop_rank,
+        groove,
     );
+    let day_agendas = day.as_ref().map_or(&[][..], |day| &day.agendas[..]);
+    let materials = material_report(handicrafts, day_agendas, &budget);
+    let still_to_get = shopping_list(handicrafts, &data.rare, day_agendas, &budget);
 
-    println!("Outputting top 5 producing agendas");
-
-    for agenda in agendas {
-        print!("[{}]", agenda.total_value);
-        let mut it = agenda
-            .handicrafts
-            .iter()
-            .zip(agenda.values.iter())
-            .peekable();
-        while let Some((handicraft, value)) = it.next() {
-            print!("{} ({})", handicraft, value);
-            if it.peek().is_some() {
-                print!(" -> ");
+    match format {
+        OutputFormat::Text => {}
+        OutputFormat::Json => {
+            let report = SolveReport {
+                agendas: agendas
+                    .iter()
+                    .take(top)
+                    .map(|agenda| AgendaReport::new(handicrafts, agenda))
+                    .collect(),
+                plan: day.as_ref().map(|day| DayReport::new(handicrafts, day)),
+                materials,
+                shopping_list: shopping_entries(&still_to_get),
+            };
+            print_json(&report);
+            return;
+        }
+        OutputFormat::Csv | OutputFormat::Code => {
+            match day {
+                Some(day) => print_shared(handicrafts, &[DayPlan::Work(day)], format),
+                None => eprintln!("Not enough rare items for any plan"),
             }
+            return;
+        }
+    }
+
+    println!("Outputting top {} producing agendas", top);
+
+    for agenda in agendas.iter().take(top) {
+        print_agenda(agenda);
+    }
+
+    println!("Outputting best plan for {} workshops", workshop_count);
+
+    match day {
+        Some(day) => {
+            for agenda in &day.agendas {
+                print_agenda(agenda);
+            }
+            println!("[{}] groove after: {}", day.total_value, day.groove.value);
+            print_material_report(&materials);
+            print_shopping_list(&still_to_get);
+        }
+        None => println!("Not enough rare items for any plan"),
+    }
+}
+
+// prices a schedule someone else made against the input's popularity and supply
+fn price_imported(
+    data: &WorkshopData,
+    input: &PlanInput,
+    shared: &str,
+    format: OutputFormat,
+) -> anyhow::Result<()> {
+    let schedule = if Path::new(shared).is_file() {
+        import_csv(&fs::read_to_string(shared)?)?
+    } else {
+        import_code(&data.handicrafts, shared)?
+    };
+    let handicrafts = input.handicrafts(data);
+    let pop_supplies = input.pop_supplies(data)?;
+    let pricing_info: HashMap<_, _> = handicrafts
+        .iter()
+        .map(|handicraft| (handicraft.name, handicraft.as_pricing_info()))
+        .collect();
+    let week = price_schedule(
+        &handicrafts,
+        &schedule,
+        &pop_supplies,
+        &pricing_info,
+        input.workshop_rank.unwrap_or_default(),
+        starting_groove(input),
+    )?;
+
+    print_week(data, input, &handicrafts, &week, format)
+}
+
+fn print_week(
+    data: &WorkshopData,
+    input: &PlanInput,
+    handicrafts: &[Handicraft],
+    week: &WeekPlan,
+    format: OutputFormat,
+) -> anyhow::Result<()> {
+    // the season's agendas are checked against everything that comes in over it
+    let on_hand = input
+        .projected_budgets(data)?
+        .pop()
+        .expect("A season has days");
+    let materials = material_report(handicrafts, week.agendas(), &on_hand);
+    let still_to_get = shopping_list(handicrafts, &data.rare, week.agendas(), &on_hand);
+    match format {
+        OutputFormat::Text => {
+            for (day, plan) in week.days.iter().enumerate() {
+                println!("Day {}", day + 1);
+                match plan {
+                    DayPlan::Rest => println!("Rest"),
+                    DayPlan::Work(agendas) => {
+                        for agenda in &agendas.agendas {
+                            print_agenda(agenda);
+                        }
+                        println!(
+                            "[{}] groove after: {}",
+                            agendas.total_value, agendas.groove.value
+                        );
+                    }
+                }
+            }
+            println!("[{}] groove after: {}", week.total_value, week.groove.value);
+            print_material_report(&materials);
+            print_shopping_list(&still_to_get);
+        }
+        OutputFormat::Json => print_json(&WeekReport::new(
+            handicrafts,
+            week,
+            materials,
+            &still_to_get,
+        )),
+        OutputFormat::Csv | OutputFormat::Code => print_shared(handicrafts, &week.days, format),
+    }
+    Ok(())
+}
+
+fn print_json<T: Serialize>(report: &T) {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(report).expect("Report failed to serialize")
+    );
+}
+
+fn print_shared(handicrafts: &[Handicraft], days: &[DayPlan], format: OutputFormat) {
+    match format {
+        OutputFormat::Csv => print!("{}", export_csv(days)),
+        OutputFormat::Code => println!("{}", export_code(handicrafts, days)),
+        OutputFormat::Text | OutputFormat::Json => unreachable!("Not a share format"),
+    }
+}
+
+fn print_material_report(report: &[MaterialUse]) {
+    println!("Materials used");
+    for usage in report {
+        print!("{}: {}", usage.material, usage.used);
+        if let Some(on_hand) = usage.on_hand {
+            print!(" of {}", on_hand);
+        }
+        if usage.shortfall() > 0 {
+            print!(", {} short", usage.shortfall());
         }
         println!();
     }
 }
 
-fn input_rare_item_count(
-    stdin: &io::Stdin,
-    input_buf: &mut String,
-    rare: RareItemVariant,
-) -> RareItemCount {
-    print!("{}: ", rare.name());
-    io::stdout().flush().unwrap();
-    stdin
-        .read_line(input_buf)
-        .expect("Tried reading user input for rare item count");
-    let count = input_buf
-        .trim()
-        .parse()
-        .expect("Must be an unsigned integer");
-    input_buf.clear();
-    RareItemCount { rare, count }
-}
-
-fn input_product_pop_supply(
-    stdin: &mut io::Stdin,
-    input_buf: &mut String,
-    handicraft: &Handicraft,
-) -> PopSupply {
-    print!("{} popularity: ", handicraft.name);
-    io::stdout().flush().unwrap();
-    stdin
-        .read_line(input_buf)
-        .expect("Tried reading user input for product popularity");
-    let popularity = input_buf.trim().parse().expect("Must be a valid character");
-    input_buf.clear();
-    print!("{} supply: ", handicraft.name);
-    io::stdout().flush().unwrap();
-    stdin
-        .read_line(input_buf)
-        .expect("Tried reading user input for product supply");
-    let supply = input_buf.trim().parse().expect("Must be a valid character");
-    input_buf.clear();
-    PopSupply { popularity, supply }
+fn print_shopping_list(list: &ShoppingList) {
+    println!("Still to get");
+    for (source, materials) in list {
+        println!("{}", source);
+        for (material, count) in materials {
+            println!("  [ ] {} x{}", material, count);
+        }
+    }
+}
+
+fn print_agenda(agenda: &Agenda) {
+    print!("[{}]", agenda.total_value);
+    let mut it = agenda
+        .handicrafts
+        .iter()
+        .zip(agenda.values.iter())
+        .peekable();
+    while let Some((handicraft, value)) = it.next() {
+        print!("{} ({})", handicraft, value);
+        if it.peek().is_some() {
+            print!(" -> ");
+        }
+    }
+    println!();
 }
@@ -0,0 +1,150 @@
+use std::{
+    cell::Cell,
+    collections::HashMap,
+    fmt,
+    str::FromStr,
+    sync::{OnceLock, RwLock},
+};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("Unknown {kind}: {name}")]
+pub struct UnknownNameError {
+    pub kind: &'static str,
+    pub name: String,
+}
+
+// names are looked up ignoring case, spaces and punctuation, so "Isleworks Potion" and
+// "IsleworksPotion" are the same handicraft
+fn lookup_key(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+// splits an identifier like IsleworksPotion into the words shown in game
+pub(crate) fn title_case(id: &str) -> String {
+    let mut title = String::with_capacity(id.len() + 4);
+    let mut prev: Option<char> = None;
+    for c in id.chars() {
+        if c.is_uppercase() && prev.is_some_and(|prev| prev.is_lowercase() || prev.is_numeric()) {
+            title.push(' ');
+        }
+        title.push(c);
+        prev = Some(c);
+    }
+    title
+}
+
+thread_local! {
+    // only the workshop data declares names, so anything else has to use ones it already loaded
+    static INTERNING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Runs `load` with deserialized names interned instead of looked up, for reading the workshop data
+pub(crate) fn interning<T>(load: impl FnOnce() -> T) -> T {
+    struct Restore(bool);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            INTERNING.with(|interning| interning.set(self.0));
+        }
+    }
+    let _restore = Restore(INTERNING.with(|interning| interning.replace(true)));
+    load()
+}
+
+/// Declares a name that is interned when the workshop data is loaded, rather than fixed in code,
+/// so new items only need adding to the data file
+macro_rules! interned_name {
+    ($(#[$attr:meta])* $name:ident, $kind:literal) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Hash)]
+        pub struct $name(&'static str);
+
+        impl $name {
+            fn interned() -> &'static RwLock<HashMap<String, &'static str>> {
+                static INTERNED: OnceLock<RwLock<HashMap<String, &'static str>>> =
+                    OnceLock::new();
+                INTERNED.get_or_init(Default::default)
+            }
+
+            /// Interns an identifier as written in the data file, e.g. `IsleworksPotion`
+            pub fn intern(id: &str) -> Self {
+                let key = lookup_key(id);
+                if let Some(id) = Self::interned()
+                    .read()
+                    .expect("Name interner poisoned")
+                    .get(&key)
+                {
+                    return $name(id);
+                }
+                let mut interned = Self::interned().write().expect("Name interner poisoned");
+                $name(
+                    interned
+                        .entry(key)
+                        .or_insert_with(|| Box::leak(id.to_string().into_boxed_str())),
+                )
+            }
+
+            /// The identifier as written in the data file
+            pub fn id(&self) -> &'static str {
+                self.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&title_case(self.0))
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.0)
+            }
+        }
+
+        /// Only finds names that have already been loaded from the workshop data
+        impl FromStr for $name {
+            type Err = UnknownNameError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                Self::interned()
+                    .read()
+                    .expect("Name interner poisoned")
+                    .get(&lookup_key(s))
+                    .map(|id| $name(id))
+                    .ok_or_else(|| UnknownNameError {
+                        kind: $kind,
+                        name: s.to_string(),
+                    })
+            }
+        }
+
+        /// Interned while loading the workshop data, and looked up like `FromStr` everywhere else
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let id = String::deserialize(deserializer)?;
+                if INTERNING.with(Cell::get) {
+                    Ok($name::intern(&id))
+                } else {
+                    id.parse().map_err(de::Error::custom)
+                }
+            }
+        }
+
+        /// Written as the identifier, so it reads back the same
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(self.0)
+            }
+        }
+    };
+}
+
+interned_name!(HandicraftName, "handicraft");
+interned_name!(MaterialName, "material");
+interned_name!(CategoryName, "category");
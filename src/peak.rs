@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::supply::DAYS_IN_SEASON;
+use crate::types::{DemandObservation, DemandShift, HandicraftName, Peak, PeakStrength, Supply};
+
+#[derive(Debug, Error)]
+pub enum PeakError {
+    #[error("Observed history is empty")]
+    EmptyHistory,
+    #[error("Day {day} of the observed history has nothing for {handicraft}")]
+    MissingObservation {
+        day: usize,
+        handicraft: HandicraftName,
+    },
+}
+
+/// Classifies the peak of every handicraft that can be pinned down from what was seen on the
+/// first days of the season, starting with Day 1. Handicrafts whose peak is still ambiguous are
+/// left out, and can be passed to the supply model as having no peak. Every handicraft seen on
+/// Day 1 has to be seen on the later days too
+pub fn predict_peaks(
+    history: &[HashMap<HandicraftName, DemandObservation>],
+) -> Result<HashMap<HandicraftName, Peak>, PeakError> {
+    let handicrafts = history.first().map(HashMap::keys).into_iter().flatten();
+    let mut peaks = HashMap::new();
+    for name in handicrafts {
+        let observations = history
+            .iter()
+            .enumerate()
+            .map(|(day, observations)| {
+                observations
+                    .get(name)
+                    .copied()
+                    .ok_or(PeakError::MissingObservation {
+                        day: day + 1,
+                        handicraft: *name,
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(peak) = predict_peak(&observations) {
+            peaks.insert(*name, peak);
+        }
+    }
+    Ok(peaks)
+}
+
+/// Classifies the peak of a handicraft from what was seen of it on each day so far, starting with
+/// Day 1
+pub fn predict_peak(observations: &[DemandObservation]) -> Option<Peak> {
+    // a peak that has already been seen shows up as a supply shortage after Day 1, lowest on the
+    // day itself, so take the lowest and the latest of those
+    let seen = observations
+        .iter()
+        .enumerate()
+        .skip(1)
+        .filter_map(|(day, observation)| {
+            let strength = match observation.pop_supply.supply {
+                Supply::Nonexistent => PeakStrength::Strong,
+                Supply::Insufficient => PeakStrength::Weak,
+                _ => return None,
+            };
+            Some(Peak {
+                day: day + 1,
+                strength,
+            })
+        })
+        .max_by_key(|peak| peak.strength);
+    if seen.is_some() {
+        return seen;
+    }
+
+    // otherwise demand rising sharply the day before gives the peak away
+    let day = observations.len();
+    let strength = match observations.last()?.demand_shift {
+        DemandShift::Skyrocketing => PeakStrength::Strong,
+        DemandShift::Increasing => PeakStrength::Weak,
+        _ => return None,
+    };
+    (day < DAYS_IN_SEASON).then_some(Peak {
+        day: day + 1,
+        strength,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::handicraft;
+    use crate::types::{PopSupply, Popularity};
+
+    fn observe(seen: &[(Supply, DemandShift)]) -> Vec<DemandObservation> {
+        seen.iter()
+            .map(|(supply, demand_shift)| DemandObservation {
+                pop_supply: PopSupply {
+                    popularity: Popularity::Average,
+                    supply: *supply,
+                },
+                demand_shift: *demand_shift,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn peaks_come_from_shortages_then_demand_shifts() {
+        use DemandShift::*;
+        use Supply::*;
+
+        let peak = |day, strength| Some(Peak { day, strength });
+        let cases = [
+            // the lowest supply after Day 1 is the peak itself, the latest if it's seen twice
+            (
+                vec![(Sufficient, Steady), (Nonexistent, Steady)],
+                peak(2, PeakStrength::Strong),
+            ),
+            (
+                vec![
+                    (Sufficient, Steady),
+                    (Sufficient, Steady),
+                    (Insufficient, Steady),
+                ],
+                peak(3, PeakStrength::Weak),
+            ),
+            (
+                vec![
+                    (Sufficient, Steady),
+                    (Insufficient, Steady),
+                    (Nonexistent, Steady),
+                ],
+                peak(3, PeakStrength::Strong),
+            ),
+            (
+                vec![
+                    (Sufficient, Steady),
+                    (Nonexistent, Steady),
+                    (Insufficient, Steady),
+                ],
+                peak(2, PeakStrength::Strong),
+            ),
+            (
+                vec![
+                    (Insufficient, Steady),
+                    (Insufficient, Steady),
+                    (Insufficient, Steady),
+                ],
+                peak(3, PeakStrength::Weak),
+            ),
+            // Day 1 shortages are left over from last season
+            (vec![(Nonexistent, Steady), (Sufficient, Steady)], None),
+            // otherwise the latest demand shift points at the next day
+            (
+                vec![(Sufficient, Steady), (Sufficient, Skyrocketing)],
+                peak(3, PeakStrength::Strong),
+            ),
+            (vec![(Sufficient, Increasing)], peak(2, PeakStrength::Weak)),
+            (vec![(Sufficient, Skyrocketing), (Sufficient, Steady)], None),
+            (vec![(Sufficient, Decreasing)], None),
+            (vec![(Sufficient, Skyrocketing); DAYS_IN_SEASON], None),
+            (vec![], None),
+        ];
+        for (seen, expected) in cases {
+            assert_eq!(predict_peak(&observe(&seen)), expected, "{:?}", seen);
+        }
+    }
+
+    #[test]
+    fn peaks_need_every_day_observed() {
+        let potion = handicraft("IsleworksPotion");
+        let firesand = handicraft("IsleworksFiresand");
+        let day = |seen: &[(HandicraftName, Supply)]| {
+            seen.iter()
+                .map(|(name, supply)| (*name, observe(&[(*supply, DemandShift::Steady)])[0]))
+                .collect::<HashMap<_, _>>()
+        };
+
+        let peaks = predict_peaks(&[
+            day(&[(potion, Supply::Sufficient), (firesand, Supply::Sufficient)]),
+            day(&[
+                (potion, Supply::Insufficient),
+                (firesand, Supply::Sufficient),
+            ]),
+        ])
+        .unwrap();
+        assert_eq!(
+            peaks,
+            HashMap::from([(
+                potion,
+                Peak {
+                    day: 2,
+                    strength: PeakStrength::Weak
+                }
+            )])
+        );
+
+        assert!(matches!(
+            predict_peaks(&[
+                day(&[(potion, Supply::Sufficient), (firesand, Supply::Sufficient)]),
+                day(&[(potion, Supply::Sufficient)]),
+            ]),
+            Err(PeakError::MissingObservation { day: 2, handicraft }) if handicraft == firesand
+        ));
+    }
+}
@@ -0,0 +1,494 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+
+use crate::agenda::{calc_agendas_with_groove, create_handicraft_graph, find_agendas};
+use crate::facilities::add_arrivals;
+use crate::supply::{SupplyState, DAYS_IN_SEASON};
+use crate::types::{
+    agenda_step, Agenda, DayPlan, Groove, GrooveAgendas, Handicraft, HandicraftName,
+    HandicraftPricingInfo, MaterialBudget, MaterialName, MaterialUse, PopSupply, Popularity,
+    PricingModifiers, WeekPlan, WorkshopRank,
+};
+
+const REST_DAYS: usize = 2;
+
+/// Every material an agenda uses
+pub fn material_usage(
+    handicrafts: &[Handicraft],
+    agenda: &[HandicraftName],
+) -> HashMap<MaterialName, usize> {
+    let mut usage = HashMap::new();
+    for (step, name) in agenda.iter().enumerate() {
+        let handicraft = agenda_step(handicrafts, name);
+        let units = handicraft.as_pricing_info().units(step > 0);
+        for (mat, count) in &handicraft.materials {
+            *usage.entry(*mat).or_default() += count * units;
+        }
+    }
+    usage
+}
+
+// only the materials that can run out
+fn budgeted_usage(
+    handicrafts: &[Handicraft],
+    budget: &MaterialBudget,
+    agenda: &[HandicraftName],
+) -> HashMap<MaterialName, usize> {
+    material_usage(handicrafts, agenda)
+        .into_iter()
+        .filter(|(mat, _)| budget.contains_key(mat))
+        .collect()
+}
+
+/// Total use of every material across the agendas of a plan, in name order, against what's in
+/// the budget
+pub fn material_report<'a, I>(
+    handicrafts: &[Handicraft],
+    agendas: I,
+    budget: &MaterialBudget,
+) -> Vec<MaterialUse>
+where
+    I: IntoIterator<Item = &'a Agenda>,
+{
+    let mut used: HashMap<MaterialName, usize> = HashMap::new();
+    for agenda in agendas {
+        for (mat, count) in material_usage(handicrafts, &agenda.handicrafts) {
+            *used.entry(mat).or_default() += count;
+        }
+    }
+    let mut report: Vec<_> = used
+        .into_iter()
+        .map(|(material, used)| MaterialUse {
+            material,
+            used,
+            on_hand: budget.get(&material).copied(),
+        })
+        .collect();
+    report.sort_by_key(|usage| usage.material);
+    report
+}
+
+/// How many of the best agendas to search for when there's a day to plan from them
+pub const DAY_PLAN_AGENDAS: usize = 5000;
+// how many of the best affordable agendas are combined when planning the day
+const DAY_PLAN_CANDIDATES: usize = 200;
+// how many of the best combinations, going by the groove the day starts with, are repriced with
+// groove carried between crafts
+const DAY_PLAN_FINALISTS: usize = 16;
+
+/// Picks one agenda per workshop from the best candidates, which may be repeated across
+/// workshops, so that the day's total is highest without using more of any material than the
+/// budget has. Candidates should be priced with the groove the day starts with
+#[allow(clippy::too_many_arguments)]
+pub fn plan_day(
+    handicrafts: &[Handicraft],
+    mut candidates: Vec<Agenda>,
+    workshop_count: usize,
+    budget: &MaterialBudget,
+    handicraft_pop_supplies: &HashMap<HandicraftName, PopSupply>,
+    handicraft_pricing_info: &HashMap<HandicraftName, HandicraftPricingInfo>,
+    workshop_rank: WorkshopRank,
+    groove: Groove,
+) -> Option<GrooveAgendas> {
+    let mut budget = budget.clone();
+    candidates.sort_by(|a, b| b.cmp(a));
+    let mut affordable = candidates
+        .into_iter()
+        .map(|agenda| {
+            let usage = budgeted_usage(handicrafts, &budget, &agenda.handicrafts);
+            DayCandidate { agenda, usage }
+        })
+        .filter(|candidate| fits_budget(&candidate.usage, &budget));
+    let mut candidates: Vec<_> = affordable.by_ref().take(DAY_PLAN_CANDIDATES).collect();
+    // every workshop can always fall back on the best agenda that needs nothing that can run out,
+    // so keep it around even when rarer agendas crowd it out of the cutoff
+    if !candidates.iter().any(DayCandidate::is_common) {
+        if let Some(fallback) = affordable.find(DayCandidate::is_common) {
+            candidates.pop();
+            candidates.push(fallback);
+        }
+    }
+
+    let mut finalists = BinaryHeap::with_capacity(DAY_PLAN_FINALISTS + 1);
+    plan_day_from(
+        &candidates,
+        workshop_count,
+        0,
+        0,
+        &mut budget,
+        &mut Vec::with_capacity(workshop_count),
+        &mut finalists,
+    );
+    finalists
+        .into_iter()
+        .map(|Reverse((_, chosen))| {
+            calc_agendas_with_groove(
+                chosen
+                    .iter()
+                    .map(|i| candidates[*i].agenda.handicrafts.clone())
+                    .collect(),
+                handicraft_pop_supplies,
+                handicraft_pricing_info,
+                workshop_rank,
+                groove,
+            )
+        })
+        .max_by_key(|day| day.total_value)
+}
+
+struct DayCandidate {
+    agenda: Agenda,
+    usage: HashMap<MaterialName, usize>,
+}
+
+impl DayCandidate {
+    fn is_common(&self) -> bool {
+        self.usage.values().all(|count| *count == 0)
+    }
+}
+
+type DayFinalists = BinaryHeap<Reverse<(usize, Vec<usize>)>>;
+
+// walks combinations with repetition of the candidates, skipping any that overspend the budget
+// and stopping once the remaining candidates can't beat the worst of the finalists
+fn plan_day_from(
+    candidates: &[DayCandidate],
+    workshop_count: usize,
+    first: usize,
+    total_value: usize,
+    budget: &mut HashMap<MaterialName, usize>,
+    chosen: &mut Vec<usize>,
+    finalists: &mut DayFinalists,
+) {
+    if chosen.len() == workshop_count {
+        finalists.push(Reverse((total_value, chosen.clone())));
+        if finalists.len() > DAY_PLAN_FINALISTS {
+            finalists.pop();
+        }
+        return;
+    }
+    let remaining = workshop_count - chosen.len();
+    for (i, candidate) in candidates.iter().enumerate().skip(first) {
+        let upper_bound = total_value + remaining * candidate.agenda.total_value;
+        if finalists.len() == DAY_PLAN_FINALISTS
+            && finalists
+                .peek()
+                .is_some_and(|Reverse((worst, _))| upper_bound <= *worst)
+        {
+            break;
+        }
+        if !fits_budget(&candidate.usage, budget) {
+            continue;
+        }
+        for (mat, count) in &candidate.usage {
+            *budget.get_mut(mat).expect("Rare item missing from budget") -= count;
+        }
+        chosen.push(i);
+        plan_day_from(
+            candidates,
+            workshop_count,
+            i,
+            total_value + candidate.agenda.total_value,
+            budget,
+            chosen,
+            finalists,
+        );
+        chosen.pop();
+        for (mat, count) in &candidate.usage {
+            *budget.get_mut(mat).expect("Rare item missing from budget") += count;
+        }
+    }
+}
+
+fn fits_budget(
+    usage: &HashMap<MaterialName, usize>,
+    budget: &HashMap<MaterialName, usize>,
+) -> bool {
+    usage
+        .iter()
+        .all(|(mat, count)| budget.get(mat).copied().unwrap_or_default() >= *count)
+}
+
+struct WeekPlanner<'a> {
+    handicrafts: &'a [Handicraft],
+    arrivals: &'a [HashMap<MaterialName, usize>],
+    popularity: HashMap<HandicraftName, Popularity>,
+    workshop_count: usize,
+    handicraft_pricing_info: HashMap<HandicraftName, HandicraftPricingInfo>,
+    workshop_rank: WorkshopRank,
+}
+
+/// Plans a whole season from each handicraft's popularity and how its supply plays out, choosing
+/// which two days to rest and what every workshop makes on the other five. The budget starts from
+/// what's on hand, and what's collected each day (see `Facilities::arrivals`) is added at the
+/// start of that day
+#[allow(clippy::too_many_arguments)]
+pub fn plan_week(
+    handicrafts: &[Handicraft],
+    popularity: &HashMap<HandicraftName, Popularity>,
+    supply: &SupplyState,
+    budget: MaterialBudget,
+    arrivals: &[HashMap<MaterialName, usize>],
+    workshop_count: usize,
+    workshop_rank: WorkshopRank,
+    groove: Groove,
+) -> WeekPlan {
+    let planner = WeekPlanner {
+        handicrafts,
+        arrivals,
+        popularity: popularity.clone(),
+        workshop_count,
+        handicraft_pricing_info: handicrafts
+            .iter()
+            .map(|handicraft| (handicraft.name, handicraft.as_pricing_info()))
+            .collect(),
+        workshop_rank,
+    };
+    plan_week_from(&planner, 1, REST_DAYS, supply, budget, groove)
+}
+
+// plans the rest of the season from the given day, resting or working on each day and keeping
+// whichever leads to the higher total
+fn plan_week_from(
+    planner: &WeekPlanner,
+    day: usize,
+    rests_left: usize,
+    supply: &SupplyState,
+    budget: MaterialBudget,
+    groove: Groove,
+) -> WeekPlan {
+    if day > DAYS_IN_SEASON {
+        return WeekPlan {
+            days: Vec::with_capacity(DAYS_IN_SEASON),
+            total_value: 0,
+            groove,
+        };
+    }
+
+    let mut budget = budget;
+    if let Some(arrivals) = planner.arrivals.get(day - 1) {
+        add_arrivals(&mut budget, arrivals);
+    }
+    let rest = (rests_left > 0).then(|| {
+        plan_week_from(
+            planner,
+            day + 1,
+            rests_left - 1,
+            supply,
+            budget.clone(),
+            groove,
+        )
+        .prepended(DayPlan::Rest)
+    });
+    let work = (DAYS_IN_SEASON - day + 1 > rests_left)
+        .then(|| plan_work_day(planner, day, rests_left, supply, budget, groove));
+
+    match (rest, work) {
+        (Some(rest), Some(work)) => {
+            if work.total_value >= rest.total_value {
+                work
+            } else {
+                rest
+            }
+        }
+        (Some(plan), None) | (None, Some(plan)) => plan,
+        (None, None) => {
+            panic!("Day {} could neither be worked nor rested", day)
+        }
+    }
+}
+
+fn plan_work_day(
+    planner: &WeekPlanner,
+    day: usize,
+    rests_left: usize,
+    supply: &SupplyState,
+    budget: MaterialBudget,
+    groove: Groove,
+) -> WeekPlan {
+    let pop_supplies = supply.pop_supplies(&planner.popularity, day);
+    let modifiers = PricingModifiers {
+        workshop_rank: planner.workshop_rank,
+        groove: groove.value,
+    };
+    let (recipe_nodes, handicraft_graph) = create_handicraft_graph(planner.handicrafts.iter());
+    let candidates = find_agendas(
+        planner.handicrafts,
+        pop_supplies.clone(),
+        budget.clone(),
+        recipe_nodes,
+        handicraft_graph,
+        planner.handicraft_pricing_info.clone(),
+        modifiers,
+        DAY_PLAN_AGENDAS,
+    );
+    let agendas = plan_day(
+        planner.handicrafts,
+        candidates,
+        planner.workshop_count,
+        &budget,
+        &pop_supplies,
+        &planner.handicraft_pricing_info,
+        planner.workshop_rank,
+        groove,
+    )
+    // nothing fits in the budget, so the workshops sit idle for the day
+    .unwrap_or(GrooveAgendas {
+        agendas: Vec::new(),
+        total_value: 0,
+        groove,
+    });
+
+    let mut supply = supply.clone();
+    let mut budget = budget;
+    for agenda in &agendas.agendas {
+        supply.record_agenda(&agenda.handicrafts, &planner.handicraft_pricing_info, day);
+        for (mat, count) in budgeted_usage(planner.handicrafts, &budget, &agenda.handicrafts) {
+            *budget.get_mut(&mat).expect("Material missing from budget") -= count;
+        }
+    }
+
+    plan_week_from(
+        planner,
+        day + 1,
+        rests_left,
+        &supply,
+        budget,
+        agendas.groove,
+    )
+    .prepended(DayPlan::Work(agendas))
+}
+
+impl WeekPlan {
+    fn prepended(mut self, day: DayPlan) -> Self {
+        self.total_value += day.total_value();
+        self.days.insert(0, day);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, handicraft, pop_supplies, pricing_info};
+    use crate::types::{material_budget, Peak, PeakStrength, RareItemCount, Supply};
+
+    #[test]
+    fn day_plans_share_a_scarce_rare_item() {
+        let data = testing::data();
+        let pop_supplies = pop_supplies(&data.handicrafts, Popularity::High, Supply::Insufficient);
+        let pricing_info = pricing_info(&data.handicrafts);
+        let modifiers = PricingModifiers {
+            workshop_rank: WorkshopRank::Three,
+            groove: 0,
+        };
+        let agendas = |budget: &MaterialBudget| {
+            let (recipe_nodes, handicraft_graph) = create_handicraft_graph(data.handicrafts.iter());
+            find_agendas(
+                &data.handicrafts,
+                pop_supplies.clone(),
+                budget.clone(),
+                recipe_nodes,
+                handicraft_graph,
+                pricing_info.clone(),
+                modifiers,
+                DAY_PLAN_AGENDAS,
+            )
+        };
+        let rare_item_counts: Vec<_> = data
+            .rare_items()
+            .map(|rare| RareItemCount { rare, count: 100 })
+            .collect();
+        let mut budget = material_budget(&rare_item_counts, &HashMap::new());
+
+        // leave only enough of a rare item the best agenda needs for one workshop to make it
+        let best = &agendas(&budget)[0];
+        let (scarce, needed) = budgeted_usage(&data.handicrafts, &budget, &best.handicrafts)
+            .into_iter()
+            .max_by_key(|(material, count)| (*count, *material))
+            .expect("Best agenda uses no rare items");
+        budget.insert(scarce, needed);
+
+        let day = plan_day(
+            &data.handicrafts,
+            agendas(&budget),
+            3,
+            &budget,
+            &pop_supplies,
+            &pricing_info,
+            modifiers.workshop_rank,
+            Groove::new(0, 35),
+        )
+        .unwrap();
+        assert_eq!(day.agendas.len(), 3);
+        for usage in material_report(&data.handicrafts, &day.agendas, &budget) {
+            assert_eq!(usage.shortfall(), 0, "{:?}", usage);
+        }
+        assert!(
+            day.agendas
+                .iter()
+                .map(
+                    |agenda| material_usage(&data.handicrafts, &agenda.handicrafts)
+                        .get(&scarce)
+                        .copied()
+                        .unwrap_or_default()
+                )
+                .sum::<usize>()
+                <= needed
+        );
+    }
+
+    #[test]
+    fn week_plans_rest_twice() {
+        let data = testing::data();
+        let popularity = data
+            .handicrafts
+            .iter()
+            .map(|handicraft| (handicraft.name, Popularity::Average))
+            .collect();
+        let supply = SupplyState::new(
+            data.handicrafts
+                .iter()
+                .map(|handicraft| (handicraft.name, Supply::Sufficient))
+                .collect(),
+            HashMap::from([(
+                handicraft("IsleworksPotion"),
+                Peak {
+                    day: 4,
+                    strength: PeakStrength::Strong,
+                },
+            )]),
+            data.supply,
+        );
+        let rare_item_counts: Vec<_> = data
+            .rare_items()
+            .map(|rare| RareItemCount { rare, count: 5 })
+            .collect();
+        let week = plan_week(
+            &data.handicrafts,
+            &popularity,
+            &supply,
+            material_budget(&rare_item_counts, &HashMap::new()),
+            &[],
+            3,
+            WorkshopRank::Three,
+            Groove::new(0, 35),
+        );
+
+        assert_eq!(week.days.len(), DAYS_IN_SEASON);
+        assert_eq!(
+            week.days
+                .iter()
+                .filter(|day| matches!(day, DayPlan::Rest))
+                .count(),
+            REST_DAYS
+        );
+        assert_eq!(
+            week.total_value,
+            week.days.iter().map(DayPlan::total_value).sum::<usize>()
+        );
+        assert!(week.total_value > 0);
+    }
+}
@@ -0,0 +1,200 @@
+use serde::Serialize;
+
+use crate::shopping::ShoppingList;
+use crate::types::{
+    agenda_step, Agenda, DayPlan, GrooveAgendas, Handicraft, HandicraftName, MaterialName,
+    MaterialUse, WeekPlan,
+};
+
+// field names here are what other tools read, so only ever add to them
+
+/// One craft in an agenda, with what it takes and what it makes
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct StepReport {
+    pub id: HandicraftName,
+    pub name: String,
+    pub hours: usize,
+    pub value: usize,
+    /// whether the craft shares a category with the one before it, so makes twice as many
+    pub efficiency_bonus: bool,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct AgendaReport {
+    pub steps: Vec<StepReport>,
+    pub hours: usize,
+    pub total_value: usize,
+}
+
+impl AgendaReport {
+    pub fn new(handicrafts: &[Handicraft], agenda: &Agenda) -> Self {
+        let steps: Vec<_> = agenda
+            .handicrafts
+            .iter()
+            .zip(&agenda.values)
+            .zip(agenda.efficiency_bonuses(handicrafts))
+            .map(|((name, value), efficiency_bonus)| {
+                let handicraft = agenda_step(handicrafts, name);
+                StepReport {
+                    id: *name,
+                    name: name.to_string(),
+                    hours: handicraft.time,
+                    value: *value,
+                    efficiency_bonus,
+                }
+            })
+            .collect();
+        AgendaReport {
+            hours: steps.iter().map(|step| step.hours).sum(),
+            steps,
+            total_value: agenda.total_value,
+        }
+    }
+}
+
+/// Agendas for every workshop over one cycle
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct DayReport {
+    pub agendas: Vec<AgendaReport>,
+    pub total_value: usize,
+    /// groove once every workshop has finished
+    pub groove: usize,
+}
+
+impl DayReport {
+    pub fn new(handicrafts: &[Handicraft], day: &GrooveAgendas) -> Self {
+        DayReport {
+            agendas: day
+                .agendas
+                .iter()
+                .map(|agenda| AgendaReport::new(handicrafts, agenda))
+                .collect(),
+            total_value: day.total_value,
+            groove: day.groove.value,
+        }
+    }
+}
+
+/// Plans for every day of a season, starting from Day 1
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct WeekReport {
+    /// rest days are left empty
+    pub days: Vec<Option<DayReport>>,
+    pub total_value: usize,
+    pub groove: usize,
+    /// over the whole season, against what's on hand once everything has come in
+    pub materials: Vec<MaterialUse>,
+    pub shopping_list: Vec<ShoppingEntry>,
+}
+
+impl WeekReport {
+    pub fn new(
+        handicrafts: &[Handicraft],
+        week: &WeekPlan,
+        materials: Vec<MaterialUse>,
+        still_to_get: &ShoppingList,
+    ) -> Self {
+        WeekReport {
+            days: week
+                .days
+                .iter()
+                .map(|day| match day {
+                    DayPlan::Rest => None,
+                    DayPlan::Work(day) => Some(DayReport::new(handicrafts, day)),
+                })
+                .collect(),
+            total_value: week.total_value,
+            groove: week.groove.value,
+            materials,
+            shopping_list: shopping_entries(still_to_get),
+        }
+    }
+}
+
+/// A material still to get, and where from
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ShoppingEntry {
+    pub source: String,
+    pub material: MaterialName,
+    pub count: usize,
+}
+
+/// Everything `solve` prints, for writing out as JSON
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct SolveReport {
+    /// the best single workshop agendas, best first
+    pub agendas: Vec<AgendaReport>,
+    /// left out when there aren't enough rare items for any plan
+    pub plan: Option<DayReport>,
+    pub materials: Vec<MaterialUse>,
+    pub shopping_list: Vec<ShoppingEntry>,
+}
+
+/// Flattens the shopping list, keeping it grouped by source
+pub fn shopping_entries(list: &ShoppingList) -> Vec<ShoppingEntry> {
+    list.iter()
+        .flat_map(|(source, materials)| {
+            materials.iter().map(|(material, count)| ShoppingEntry {
+                source: source.to_string(),
+                material: *material,
+                count: *count,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::agenda::evaluate;
+    use crate::testing::{self, handicraft, pop_supplies};
+    use crate::types::{Groove, Popularity, Supply, WorkshopRank};
+
+    fn keys(value: &Value) -> Vec<&str> {
+        let mut keys: Vec<_> = value
+            .as_object()
+            .expect("Report is an object")
+            .keys()
+            .map(String::as_str)
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn reports_keep_their_json_keys() {
+        let data = testing::data();
+        let (potion, firesand) = (
+            handicraft("IsleworksPotion"),
+            handicraft("IsleworksFiresand"),
+        );
+        let day = evaluate(
+            &data.handicrafts,
+            &[potion, firesand],
+            &pop_supplies(&data.handicrafts, Popularity::High, Supply::Sufficient),
+            WorkshopRank::Three,
+            Groove::new(0, 35),
+        )
+        .unwrap();
+
+        let json = serde_json::to_value(DayReport::new(&data.handicrafts, &day)).unwrap();
+        assert_eq!(keys(&json), ["agendas", "groove", "total_value"]);
+        assert_eq!(json["groove"], json!(day.groove.value));
+        let agenda = &json["agendas"][0];
+        assert_eq!(keys(agenda), ["hours", "steps", "total_value"]);
+        assert_eq!(agenda["hours"], json!(8));
+        let step = &agenda["steps"][1];
+        assert_eq!(
+            keys(step),
+            ["efficiency_bonus", "hours", "id", "name", "value"]
+        );
+        assert_eq!(step["id"], json!("IsleworksFiresand"));
+        assert_eq!(step["name"], json!("Isleworks Firesand"));
+        assert_eq!(step["hours"], json!(4));
+        assert_eq!(step["value"], json!(day.agendas[0].values[1]));
+        assert_eq!(step["efficiency_bonus"], json!(true));
+        assert_eq!(agenda["steps"][0]["efficiency_bonus"], json!(false));
+    }
+}
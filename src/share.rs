@@ -0,0 +1,381 @@
+use std::{collections::HashMap, fmt::Write};
+
+use thiserror::Error;
+
+use crate::agenda::{calc_agendas_with_groove, check_agenda, AgendaError};
+use crate::types::{
+    agenda_step, DayPlan, Groove, Handicraft, HandicraftName, HandicraftPricingInfo, PopSupply,
+    WeekPlan, WorkshopRank,
+};
+
+const CSV_HEADER: &str = "day,workshop,slot,handicraft,value";
+// share codes separate days, then workshops, and write each craft as two base 36 digits
+const DAY_SEPARATOR: char = '/';
+const WORKSHOP_SEPARATOR: char = '-';
+// follows a workshop with how many workshops in a row run the same agenda
+const REPEAT_MARKER: char = '*';
+const CODE_RADIX: u32 = 36;
+const CODE_DIGITS: usize = 2;
+/// Handicraft codes have to be below this to fit in a share code
+pub const CODE_LIMIT: usize = (CODE_RADIX * CODE_RADIX) as usize;
+// bumped whenever the code changes, so old codes are rejected instead of misread
+const CODE_VERSION: &str = "2:";
+
+/// Handicrafts made in each workshop on each day, starting with Day 1. Rest days have no workshops
+pub type Schedule = Vec<Vec<Vec<HandicraftName>>>;
+
+#[derive(Debug, Error)]
+pub enum ShareError {
+    #[error("Line {line} of the schedule: {reason}")]
+    Csv { line: usize, reason: String },
+    #[error("Malformed share code: {0}")]
+    Code(String),
+    #[error("No handicraft has share code {0}")]
+    UnknownCode(String),
+    #[error("Day {day}, workshop {workshop}: {source}")]
+    Agenda {
+        day: usize,
+        workshop: usize,
+        source: AgendaError,
+    },
+}
+
+/// The handicrafts of every agenda in the plan, dropping their values
+pub fn schedule(days: &[DayPlan]) -> Schedule {
+    days.iter()
+        .map(|day| match day {
+            DayPlan::Rest => Vec::new(),
+            DayPlan::Work(agendas) => agendas
+                .agendas
+                .iter()
+                .map(|agenda| agenda.handicrafts.clone())
+                .collect(),
+        })
+        .collect()
+}
+
+/// One row per workshop slot, numbering days, workshops and slots from 1. Rest days get a row of
+/// their own with only the day filled in, so the plan reads back the same length
+pub fn export_csv(days: &[DayPlan]) -> String {
+    let mut csv = String::from(CSV_HEADER);
+    csv.push('\n');
+    for (day, plan) in days.iter().enumerate() {
+        let DayPlan::Work(agendas) = plan else {
+            writeln!(csv, "{},,,,", day + 1).expect("Writing to a string can't fail");
+            continue;
+        };
+        for (workshop, agenda) in agendas.agendas.iter().enumerate() {
+            for (slot, (handicraft, value)) in
+                agenda.handicrafts.iter().zip(&agenda.values).enumerate()
+            {
+                writeln!(
+                    csv,
+                    "{},{},{},{},{}",
+                    day + 1,
+                    workshop + 1,
+                    slot + 1,
+                    handicraft,
+                    value
+                )
+                .expect("Writing to a string can't fail");
+            }
+        }
+    }
+    csv
+}
+
+/// Reads back a CSV written by `export_csv`. Values are ignored, since they depend on whoever
+/// made the schedule's popularity and supply, and slots can be in any order
+pub fn import_csv(csv: &str) -> Result<Schedule, ShareError> {
+    let mut rows = Vec::new();
+    // the last day with a rest row, which may come after every working day
+    let mut rest_days = 0;
+    for (i, line) in csv.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || (i == 0 && line.starts_with("day")) {
+            continue;
+        }
+        let error = |reason: String| ShareError::Csv {
+            line: i + 1,
+            reason,
+        };
+        let fields: Vec<_> = line.split(',').map(str::trim).collect();
+        let [day, workshop, slot, handicraft, ..] = fields[..] else {
+            return Err(error(format!(
+                "Expected at least 4 columns, got {}",
+                fields.len()
+            )));
+        };
+        let number = |field: &str, column| match field.parse::<usize>() {
+            Ok(number) if number > 0 => Ok(number),
+            _ => Err(error(format!(
+                "{} should be a number from 1, got {}",
+                column, field
+            ))),
+        };
+        let day = number(day, "day")?;
+        if [workshop, slot, handicraft]
+            .iter()
+            .all(|field| field.is_empty())
+        {
+            rest_days = rest_days.max(day);
+            continue;
+        }
+        rows.push((
+            day,
+            number(workshop, "workshop")?,
+            number(slot, "slot")?,
+            handicraft
+                .parse::<HandicraftName>()
+                .map_err(|e| error(e.to_string()))?,
+        ));
+    }
+    rows.sort_by_key(|(day, workshop, slot, _)| (*day, *workshop, *slot));
+
+    let mut schedule = vec![Vec::new(); rest_days];
+    for (day, workshop, _, handicraft) in rows {
+        if schedule.len() < day {
+            schedule.resize_with(day, Vec::new);
+        }
+        let workshops = &mut schedule[day - 1];
+        if workshops.len() < workshop {
+            workshops.resize_with(workshop, Vec::new);
+        }
+        workshops[workshop - 1].push(handicraft);
+    }
+    Ok(schedule)
+}
+
+/// A short code for the plan after the format version, writing each handicraft as its code in two
+/// base 36 digits. Workshops in a row with the same agenda are written once with a count, e.g.
+/// `2:0102*3-0201` for three workshops making a Potion then a Firesand and one the other way round,
+/// all on one day. Rest days are left empty
+pub fn export_code(handicrafts: &[Handicraft], days: &[DayPlan]) -> String {
+    let days: Vec<_> = schedule(days)
+        .iter()
+        .map(|workshops| {
+            let agendas: Vec<String> = workshops
+                .iter()
+                .map(|agenda| {
+                    agenda
+                        .iter()
+                        .map(|name| craft_code(agenda_step(handicrafts, name).code))
+                        .collect()
+                })
+                .collect();
+            let mut runs: Vec<(&String, usize)> = Vec::new();
+            for agenda in &agendas {
+                match runs.last_mut() {
+                    Some((last, count)) if *last == agenda => *count += 1,
+                    _ => runs.push((agenda, 1)),
+                }
+            }
+            runs.iter()
+                .map(|(agenda, count)| match count {
+                    1 => agenda.to_string(),
+                    _ => format!("{}{}{}", agenda, REPEAT_MARKER, count),
+                })
+                .collect::<Vec<_>>()
+                .join(&WORKSHOP_SEPARATOR.to_string())
+        })
+        .collect();
+    format!("{}{}", CODE_VERSION, days.join(&DAY_SEPARATOR.to_string()))
+}
+
+fn craft_code(code: usize) -> String {
+    let digit = |value: usize| {
+        char::from_digit((value % CODE_RADIX as usize) as u32, CODE_RADIX)
+            .expect("Remainder is below the radix")
+    };
+    [digit(code / CODE_RADIX as usize), digit(code)]
+        .iter()
+        .collect()
+}
+
+/// Reads back a code written by `export_code`
+pub fn import_code(handicrafts: &[Handicraft], code: &str) -> Result<Schedule, ShareError> {
+    let malformed = || ShareError::Code(code.to_string());
+    let days = code
+        .trim()
+        .strip_prefix(CODE_VERSION)
+        .ok_or_else(malformed)?;
+    if days.is_empty() {
+        return Err(malformed());
+    }
+    let mut schedule = Vec::new();
+    for day in days.split(DAY_SEPARATOR) {
+        let mut workshops = Vec::new();
+        if day.is_empty() {
+            schedule.push(workshops);
+            continue;
+        }
+        for workshop in day.split(WORKSHOP_SEPARATOR) {
+            let (crafts, count) = match workshop.split_once(REPEAT_MARKER) {
+                Some((crafts, count)) => match count.parse::<usize>() {
+                    Ok(count) if count > 1 => (crafts, count),
+                    _ => return Err(malformed()),
+                },
+                None => (workshop, 1),
+            };
+            let digits: Vec<_> = crafts.chars().collect();
+            if digits.len() % CODE_DIGITS != 0 {
+                return Err(malformed());
+            }
+            let agenda = digits
+                .chunks(CODE_DIGITS)
+                .map(|digits| {
+                    let craft: String = digits.iter().collect();
+                    let number =
+                        usize::from_str_radix(&craft, CODE_RADIX).map_err(|_| malformed())?;
+                    handicrafts
+                        .iter()
+                        .find(|handicraft| handicraft.code == number)
+                        .map(|handicraft| handicraft.name)
+                        .ok_or(ShareError::UnknownCode(craft))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            workshops.extend(std::iter::repeat_n(agenda, count));
+        }
+        schedule.push(workshops);
+    }
+    Ok(schedule)
+}
+
+/// Checks every workshop's agenda the same way `evaluate` does. Workshops with no crafts are left
+/// idle
+pub fn check_schedule(handicrafts: &[Handicraft], schedule: &Schedule) -> Result<(), ShareError> {
+    for (day, workshops) in schedule.iter().enumerate() {
+        for (workshop, agenda) in workshops.iter().enumerate() {
+            if agenda.is_empty() {
+                continue;
+            }
+            check_agenda(handicrafts, agenda).map_err(|source| ShareError::Agenda {
+                day: day + 1,
+                workshop: workshop + 1,
+                source,
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Prices every day of a schedule with the same popularity and supply, carrying groove from one
+/// day to the next, once `check_schedule` has passed it
+pub fn price_schedule(
+    handicrafts: &[Handicraft],
+    schedule: &Schedule,
+    handicraft_pop_supplies: &HashMap<HandicraftName, PopSupply>,
+    handicraft_pricing_info: &HashMap<HandicraftName, HandicraftPricingInfo>,
+    workshop_rank: WorkshopRank,
+    mut groove: Groove,
+) -> Result<WeekPlan, ShareError> {
+    check_schedule(handicrafts, schedule)?;
+    let days: Vec<_> = schedule
+        .iter()
+        .map(|workshops| {
+            if workshops.is_empty() {
+                return DayPlan::Rest;
+            }
+            let agendas = calc_agendas_with_groove(
+                workshops.clone(),
+                handicraft_pop_supplies,
+                handicraft_pricing_info,
+                workshop_rank,
+                groove,
+            );
+            groove = agendas.groove;
+            DayPlan::Work(agendas)
+        })
+        .collect();
+    Ok(WeekPlan {
+        total_value: days.iter().map(DayPlan::total_value).sum(),
+        days,
+        groove,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, handicraft, pop_supplies, pricing_info};
+    use crate::types::{Popularity, Supply};
+
+    #[test]
+    fn shared_plans_read_back_the_same() {
+        let data = testing::data();
+        let potion = handicraft("IsleworksPotion");
+        let firesand = handicraft("IsleworksFiresand");
+        let shared: Schedule = vec![
+            vec![],
+            vec![
+                vec![potion, firesand, potion, firesand],
+                vec![firesand, potion, firesand, potion],
+            ],
+            vec![],
+            vec![vec![firesand, potion, firesand, potion]],
+            vec![],
+        ];
+        let pop_supplies = pop_supplies(&data.handicrafts, Popularity::High, Supply::Insufficient);
+        let pricing_info = pricing_info(&data.handicrafts);
+        let plan = price_schedule(
+            &data.handicrafts,
+            &shared,
+            &pop_supplies,
+            &pricing_info,
+            WorkshopRank::Three,
+            Groove::new(4, 35),
+        )
+        .unwrap();
+        assert_eq!(schedule(&plan.days), shared);
+
+        let csv = export_csv(&plan.days);
+        let mut lines = csv.lines().skip(1);
+        assert_eq!(lines.next(), Some("1,,,,"));
+        assert!(lines.next().unwrap().starts_with("2,1,1,Isleworks Potion,"));
+        assert_eq!(import_csv(&csv).unwrap(), shared);
+
+        let code = export_code(&data.handicrafts, &plan.days);
+        assert_eq!(code, "2:/01020102-02010201//02010201/");
+        assert_eq!(import_code(&data.handicrafts, &code).unwrap(), shared);
+        let repeated = vec![vec![vec![potion, firesand]; 3], vec![vec![firesand]]];
+        assert_eq!(
+            import_code(&data.handicrafts, "2:0102*3/02").unwrap(),
+            repeated
+        );
+
+        assert!(matches!(
+            import_csv("day,workshop,slot,handicraft,value\n1,1,0,Isleworks Potion,10"),
+            Err(ShareError::Csv { line: 2, .. })
+        ));
+        let chair = handicraft("IsleworksWoodenChair");
+        assert!(matches!(
+            price_schedule(
+                &data.handicrafts,
+                &vec![vec![vec![potion], vec![potion, chair, potion]]],
+                &pop_supplies,
+                &pricing_info,
+                WorkshopRank::Three,
+                Groove::new(4, 35),
+            ),
+            Err(ShareError::Agenda {
+                day: 1,
+                workshop: 2,
+                source: AgendaError::NoSharedCategory { step: 2, .. },
+            })
+        ));
+        for malformed in ["1:0102", "2:010", "2:0102*1", "2:01!2"] {
+            assert!(
+                matches!(
+                    import_code(&data.handicrafts, malformed),
+                    Err(ShareError::Code(_))
+                ),
+                "{}",
+                malformed
+            );
+        }
+        assert!(matches!(
+            import_code(&data.handicrafts, "2:01zz"),
+            Err(ShareError::UnknownCode(code)) if code == "zz"
+        ));
+    }
+}
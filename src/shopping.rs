@@ -0,0 +1,134 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
+use crate::name::title_case;
+use crate::plan::material_usage;
+use crate::types::{Agenda, Handicraft, MaterialBudget, MaterialName, RareItems};
+
+/// Where a material has to be got from
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MaterialSource {
+    /// rare materials found on expeditions to an area
+    Area(String),
+    /// pasture leavings
+    Leavings,
+    /// cropland produce
+    Produce,
+    /// gathering nodes around the island
+    Gathered,
+}
+
+impl MaterialSource {
+    pub fn of(material: MaterialName, rare: &RareItems) -> Self {
+        if let Some(item) = rare.material.iter().find(|item| item.name == material) {
+            MaterialSource::Area(item.area.clone())
+        } else if rare.leavings.iter().any(|item| item.name == material) {
+            MaterialSource::Leavings
+        } else if rare.produce.iter().any(|item| item.name == material) {
+            MaterialSource::Produce
+        } else {
+            MaterialSource::Gathered
+        }
+    }
+}
+
+impl fmt::Display for MaterialSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MaterialSource::Area(area) => f.write_str(&title_case(area)),
+            MaterialSource::Leavings => f.write_str("Pasture"),
+            MaterialSource::Produce => f.write_str("Cropland"),
+            MaterialSource::Gathered => f.write_str("Gathering"),
+        }
+    }
+}
+
+/// Materials still to get, grouped by source with each group in name order
+pub type ShoppingList = BTreeMap<MaterialSource, Vec<(MaterialName, usize)>>;
+
+/// Everything the agendas of a plan use, less whatever is already on hand
+pub fn shopping_list<'a, I>(
+    handicrafts: &[Handicraft],
+    rare: &RareItems,
+    agendas: I,
+    on_hand: &MaterialBudget,
+) -> ShoppingList
+where
+    I: IntoIterator<Item = &'a Agenda>,
+{
+    let mut needed: HashMap<MaterialName, usize> = HashMap::new();
+    for agenda in agendas {
+        for (mat, count) in material_usage(handicrafts, &agenda.handicrafts) {
+            *needed.entry(mat).or_default() += count;
+        }
+    }
+
+    let mut list = ShoppingList::new();
+    for (mat, count) in needed {
+        let missing = count.saturating_sub(on_hand.get(&mat).copied().unwrap_or_default());
+        if missing > 0 {
+            list.entry(MaterialSource::of(mat, rare))
+                .or_default()
+                .push((mat, missing));
+        }
+    }
+    for materials in list.values_mut() {
+        materials.sort();
+    }
+    list
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, handicraft, material};
+
+    #[test]
+    fn shopping_list_groups_what_is_missing_by_source() {
+        let data = testing::data();
+        let agendas: Vec<_> = [
+            "IsleworksButter",
+            "IsleworksGarnetRapier",
+            "IsleworksSweetPopoto",
+        ]
+        .into_iter()
+        .map(|id| Agenda {
+            handicrafts: vec![handicraft(id)],
+            values: vec![0],
+            total_value: 0,
+        })
+        .collect();
+        let on_hand = MaterialBudget::from([
+            (material("SanctuaryMilk"), 1),
+            (material("RawIslandGarnet"), 1),
+            (material("IslandCopperOre"), 5),
+        ]);
+
+        let list = shopping_list(&data.handicrafts, &data.rare, &agendas, &on_hand);
+        let mut gathered = vec![
+            (material("IslandRockSalt"), 3),
+            (material("IslandTinsand"), 3),
+            (material("IslandSap"), 3),
+        ];
+        gathered.sort();
+        assert_eq!(
+            list,
+            ShoppingList::from([
+                (
+                    MaterialSource::Area("FatalFalls".to_string()),
+                    vec![(material("RawIslandGarnet"), 1)]
+                ),
+                (
+                    MaterialSource::Leavings,
+                    vec![(material("SanctuaryMilk"), 1)]
+                ),
+                (MaterialSource::Produce, vec![(material("IslandPopoto"), 2)]),
+                (MaterialSource::Gathered, gathered),
+            ])
+        );
+        let sources: Vec<_> = list.keys().map(ToString::to_string).collect();
+        assert_eq!(sources, ["Fatal Falls", "Pasture", "Cropland", "Gathering"]);
+    }
+}
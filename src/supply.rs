@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+
+use crate::peak::{predict_peaks, PeakError};
+use crate::types::{
+    DemandObservation, HandicraftName, HandicraftPricingInfo, Peak, PeakStrength, PopSupply,
+    Popularity, Supply, SupplyModel,
+};
+
+pub const DAYS_IN_SEASON: usize = 7;
+
+/// Projected supply of every handicraft over a season, from the supply seen on Day 1, each
+/// handicraft's predicted peak, and what has been made on earlier days
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SupplyState {
+    day_one: HashMap<HandicraftName, Supply>,
+    peaks: HashMap<HandicraftName, Peak>,
+    model: SupplyModel,
+    /// units made of each handicraft on each day of the season
+    produced: HashMap<HandicraftName, [usize; DAYS_IN_SEASON]>,
+}
+
+impl SupplyState {
+    pub fn new(
+        day_one: HashMap<HandicraftName, Supply>,
+        peaks: HashMap<HandicraftName, Peak>,
+        model: SupplyModel,
+    ) -> Self {
+        SupplyState {
+            day_one,
+            peaks,
+            model,
+            produced: HashMap::new(),
+        }
+    }
+
+    /// Starts from Day 1 of the observed history, with peaks predicted from the whole of it
+    pub fn from_history(
+        history: &[HashMap<HandicraftName, DemandObservation>],
+        model: SupplyModel,
+    ) -> Result<Self, PeakError> {
+        Ok(SupplyState::new(
+            history
+                .first()
+                .ok_or(PeakError::EmptyHistory)?
+                .iter()
+                .map(|(name, observation)| (*name, observation.pop_supply.supply))
+                .collect(),
+            predict_peaks(history)?,
+            model,
+        ))
+    }
+
+    /// Records units of a handicraft made on a day (1-7), which raise its supply from the next
+    /// day on
+    pub fn record_production(&mut self, handicraft: HandicraftName, day: usize, units: usize) {
+        self.produced.entry(handicraft).or_default()[day - 1] += units;
+    }
+
+    /// Records every craft in an agenda run on a day (1-7). Crafts with the efficiency bonus make
+    /// twice as many units
+    pub fn record_agenda(
+        &mut self,
+        handicrafts: &[HandicraftName],
+        handicraft_pricing_info: &HashMap<HandicraftName, HandicraftPricingInfo>,
+        day: usize,
+    ) {
+        for (step, handicraft) in handicrafts.iter().enumerate() {
+            let pricing_info = handicraft_pricing_info.get(handicraft).unwrap_or_else(|| {
+                panic!(
+                    "Agenda had handicraft without pricing info ({})",
+                    handicraft
+                )
+            });
+            self.record_production(*handicraft, day, pricing_info.units(step > 0));
+        }
+    }
+
+    /// Supply of a handicraft on a day (1-7), before anything is made that day
+    pub fn supply(&self, handicraft: HandicraftName, day: usize) -> Supply {
+        let day_one = *self
+            .day_one
+            .get(&handicraft)
+            .unwrap_or_else(|| panic!("Handicraft without Day 1 supply ({})", handicraft));
+        // supply settles back to sufficient once an item's peak has passed
+        let baseline = match self.peaks.get(&handicraft) {
+            Some(peak) if day == peak.day => match peak.strength {
+                PeakStrength::Strong => Supply::Nonexistent,
+                PeakStrength::Weak => Supply::Insufficient,
+            },
+            Some(peak) if day > peak.day => Supply::Sufficient,
+            _ => day_one,
+        };
+        let produced: usize = self
+            .produced
+            .get(&handicraft)
+            .map(|produced| produced[..day - 1].iter().sum())
+            .unwrap_or_default();
+        baseline.raised(produced / self.model.units_per_level)
+    }
+
+    pub fn multiplier(&self, handicraft: HandicraftName, day: usize) -> f64 {
+        self.supply(handicraft, day).multiplier()
+    }
+
+    /// Popularity and projected supply of every handicraft on a day (1-7), for scoring agendas
+    pub fn pop_supplies(
+        &self,
+        popularity: &HashMap<HandicraftName, Popularity>,
+        day: usize,
+    ) -> HashMap<HandicraftName, PopSupply> {
+        popularity
+            .iter()
+            .map(|(name, popularity)| {
+                (
+                    *name,
+                    PopSupply {
+                        popularity: *popularity,
+                        supply: self.supply(*name, day),
+                    },
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::handicraft;
+    use crate::types::DemandShift;
+
+    #[test]
+    fn supply_rises_with_production_and_bottoms_out_at_peaks() {
+        assert_eq!(Supply::Insufficient.raised(0), Supply::Insufficient);
+        assert_eq!(Supply::Insufficient.raised(2), Supply::Surplus);
+        assert_eq!(Supply::Surplus.raised(5), Supply::Overflowing);
+        assert_eq!(Supply::Overflowing.raised(1), Supply::Overflowing);
+
+        let (potion, firesand) = (
+            handicraft("IsleworksPotion"),
+            handicraft("IsleworksFiresand"),
+        );
+        let model = SupplyModel { units_per_level: 4 };
+        let mut supply = SupplyState::new(
+            HashMap::from([(potion, Supply::Insufficient), (firesand, Supply::Surplus)]),
+            HashMap::from([(
+                firesand,
+                Peak {
+                    day: 3,
+                    strength: PeakStrength::Strong,
+                },
+            )]),
+            model,
+        );
+
+        // what's made on a day only counts from the next
+        supply.record_production(potion, 2, model.units_per_level - 1);
+        assert_eq!(supply.supply(potion, 2), Supply::Insufficient);
+        assert_eq!(supply.supply(potion, 3), Supply::Insufficient);
+        supply.record_production(potion, 2, 1);
+        assert_eq!(supply.supply(potion, 2), Supply::Insufficient);
+        assert_eq!(supply.supply(potion, 3), Supply::Sufficient);
+        supply.record_production(potion, 3, 3 * model.units_per_level);
+        assert_eq!(supply.supply(potion, 4), Supply::Overflowing);
+
+        // the peak replaces the Day 1 supply on its day, then it settles back to sufficient
+        assert_eq!(supply.supply(firesand, 2), Supply::Surplus);
+        assert_eq!(supply.supply(firesand, 3), Supply::Nonexistent);
+        assert_eq!(supply.supply(firesand, 4), Supply::Sufficient);
+        supply.record_production(firesand, 1, model.units_per_level);
+        assert_eq!(supply.supply(firesand, 3), Supply::Insufficient);
+    }
+
+    #[test]
+    fn supply_starts_from_the_history_with_peaks_predicted_from_it() {
+        let (potion, firesand) = (
+            handicraft("IsleworksPotion"),
+            handicraft("IsleworksFiresand"),
+        );
+        let seen = |supply, demand_shift| DemandObservation {
+            pop_supply: PopSupply {
+                popularity: Popularity::High,
+                supply,
+            },
+            demand_shift,
+        };
+        let history = [
+            HashMap::from([
+                (potion, seen(Supply::Surplus, DemandShift::Steady)),
+                (firesand, seen(Supply::Sufficient, DemandShift::Steady)),
+            ]),
+            HashMap::from([
+                (potion, seen(Supply::Insufficient, DemandShift::Steady)),
+                (
+                    firesand,
+                    seen(Supply::Sufficient, DemandShift::Skyrocketing),
+                ),
+            ]),
+        ];
+        let supply = SupplyState::from_history(&history, SupplyModel::default()).unwrap();
+
+        // the potion peaked weakly on Day 2, and the firesand's arrows point at a strong peak on
+        // Day 3
+        assert_eq!(supply.supply(potion, 1), Supply::Surplus);
+        assert_eq!(supply.supply(potion, 2), Supply::Insufficient);
+        assert_eq!(supply.supply(potion, 3), Supply::Sufficient);
+        assert_eq!(supply.supply(firesand, 2), Supply::Sufficient);
+        assert_eq!(supply.supply(firesand, 3), Supply::Nonexistent);
+        assert_eq!(
+            supply.pop_supplies(&HashMap::from([(firesand, Popularity::Low)]), 3),
+            HashMap::from([(
+                firesand,
+                PopSupply {
+                    popularity: Popularity::Low,
+                    supply: Supply::Nonexistent,
+                }
+            )])
+        );
+
+        assert!(matches!(
+            SupplyState::from_history(&[], SupplyModel::default()),
+            Err(PeakError::EmptyHistory)
+        ));
+    }
+}
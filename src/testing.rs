@@ -0,0 +1,45 @@
+//! Setup shared by the unit tests, all of it on the bundled data
+
+use std::{collections::HashMap, sync::OnceLock};
+
+use crate::types::{
+    Handicraft, HandicraftName, HandicraftPricingInfo, MaterialName, PopSupply, Popularity, Supply,
+    WorkshopData,
+};
+
+/// The bundled data, loaded once so that every name in it is interned
+pub(crate) fn data() -> &'static WorkshopData {
+    static DATA: OnceLock<WorkshopData> = OnceLock::new();
+    DATA.get_or_init(|| WorkshopData::try_default().unwrap())
+}
+
+pub(crate) fn handicraft(id: &str) -> HandicraftName {
+    data();
+    id.parse().unwrap()
+}
+
+pub(crate) fn material(id: &str) -> MaterialName {
+    data();
+    id.parse().unwrap()
+}
+
+/// The same popularity and supply for every handicraft
+pub(crate) fn pop_supplies(
+    handicrafts: &[Handicraft],
+    popularity: Popularity,
+    supply: Supply,
+) -> HashMap<HandicraftName, PopSupply> {
+    handicrafts
+        .iter()
+        .map(|handicraft| (handicraft.name, PopSupply { popularity, supply }))
+        .collect()
+}
+
+pub(crate) fn pricing_info(
+    handicrafts: &[Handicraft],
+) -> HashMap<HandicraftName, HandicraftPricingInfo> {
+    handicrafts
+        .iter()
+        .map(|handicraft| (handicraft.name, handicraft.as_pricing_info()))
+        .collect()
+}
@@ -1,162 +1,18 @@
-use std::{collections::HashMap, str::FromStr};
+use std::{collections::HashMap, io, iter, path::PathBuf, str::FromStr};
 
 use derive_more::Unwrap;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-#[derive(
-    Deserialize, Debug, Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Hash, strum_macros::Display,
-)]
-#[strum(serialize_all = "title_case")]
-pub enum HandicraftName {
-    IsleworksPotion,
-    IsleworksFiresand,
-    IsleworksWoodenChair,
-    IsleworksGrilledClam,
-    IsleworksNecklace,
-    IsleworksCoralRing,
-    IsleworksBarbut,
-    IsleworksMacuahuitl,
-    IsleworksSauerkraut,
-    IsleworksBakedPumpkin,
-    IsleworksTunic,
-    IsleworksCulinaryKnife,
-    IsleworksBrush,
-    IsleworksBoiledEgg,
-    IsleworksHora,
-    IsleworksEarrings,
-    IsleworksButter,
-    IsleworksBrickCounter,
-    BronzeSheep,
-    IsleworksGrowthFormula,
-    IsleworksGarnetRapier,
-    IsleworksSpruceRoundShield,
-    IsleworksSharkOil,
-    IsleworksSilverEarCuffs,
-    IsleworksSweetPopoto,
-    IsleworksParsnipSalad,
-    IsleworksCaramels,
-    IsleworksRibbon,
-    IsleworksRope,
-    IsleworksCavaliersHat,
-    IsleworksHorn,
-    IsleworksSaltCod,
-    IsleworksSquidInk,
-    IsleworksEssentialDraught,
-    IsleberryJam,
-    IsleworksTomatoRelish,
-    IsleworksOnionSoup,
-    IslefishPie,
-    IsleworksCornFlakes,
-    IsleworksPickledRadish,
-    IsleworksIronAxe,
-    IsleworksQuartzRing,
-    IsleworksPorcelainVase,
-    IsleworksVegetableJuice,
-    IsleworksPumpkinPudding,
-    IsleworksSheepfluffRug,
-    IsleworksGardenScythe,
-    IsleworksBed,
-    IsleworksScaleFingers,
-    IsleworksCrook,
-}
-
-#[derive(
-    Deserialize,
-    Debug,
-    Clone,
-    Copy,
-    PartialOrd,
-    Ord,
-    PartialEq,
-    Eq,
-    Hash,
-    strum_macros::Display,
-    strum_macros::EnumString,
-)]
-#[strum(serialize_all = "title_case")]
-pub enum MaterialName {
-    IslandAlyssum,
-    IslandApple,
-    IslandBranch,
-    IslandCabbage,
-    IslandClam,
-    IslandClay,
-    IslandCopperOre,
-    IslandCoral,
-    IslandCorn,
-    IslandCottonBoll,
-    IslandHammerhead,
-    IslandHemp,
-    IslandJellyfish,
-    IslandLaver,
-    IslandLimestone,
-    IslandLog,
-    IslandOnion,
-    IslandPalmLeaf,
-    IslandPalmLog,
-    IslandParsnip,
-    IslandPopoto,
-    IslandPumpkin,
-    IslandRadish,
-    IslandRockSalt,
-    IslandSand,
-    IslandSap,
-    IslandSilverOre,
-    IslandSpruceLog,
-    IslandSquid,
-    IslandStone,
-    IslandSugarcane,
-    IslandTinsand,
-    IslandIronOre,
-    IslandQuartz,
-    IslandLeucogranite,
-    IslandTomato,
-    IslandVine,
-    IslandWheat,
-    Isleberry,
-    Islefish,
-    Islewort,
-    RawIslandGarnet,
-    SanctuaryCarapace,
-    SanctuaryClaw,
-    SanctuaryEgg,
-    SanctuaryFang,
-    SanctuaryFeather,
-    SanctuaryFleece,
-    SanctuaryFur,
-    SanctuaryHorn,
-    SanctuaryMilk,
-}
-#[derive(
-    Deserialize, Debug, Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Hash, strum_macros::Display,
-)]
-#[strum(serialize_all = "title_case")]
-pub enum CategoryName {
-    Accessories,
-    Arms,
-    Attire,
-    Concoctions,
-    Confections,
-    CreatureCreations,
-    Foodstuffs,
-    Furnishings,
-    Ingredients,
-    MarineMerchandise,
-    Metalworks,
-    PreservedFood,
-    Sundries,
-    Textiles,
-    UnburiedTreasures,
-    Woodworks,
-}
+pub use crate::name::{CategoryName, HandicraftName, MaterialName, UnknownNameError};
+use crate::validate::DataValidationErrors;
 
 // for the graph
-// #[derive(Debug, Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Hash, Unwrap)]
-// pub enum MaterialGraphNode {
-//     Handicraft(HandicraftName),
-//     Material(MaterialName),
-// }
+#[derive(Debug, Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Hash, Unwrap)]
+pub enum MaterialGraphNode {
+    Handicraft(HandicraftName),
+    Material(MaterialName),
+}
 
 #[derive(Debug, Clone, Copy, PartialOrd, Ord, PartialEq, Eq, Hash, Unwrap)]
 pub enum HandicraftGraphNode {
@@ -167,6 +23,11 @@ pub enum HandicraftGraphNode {
 #[derive(Deserialize, Debug, Clone)]
 pub struct Handicraft {
     pub name: HandicraftName,
+    /// number standing for the handicraft in share codes
+    pub code: usize,
+    /// island sanctuary rank the handicraft unlocks at
+    #[serde(default = "first_rank")]
+    pub rank: usize,
     pub time: usize,
     pub quantity: usize,
     pub value: usize,
@@ -174,7 +35,37 @@ pub struct Handicraft {
     pub materials: HashMap<MaterialName, usize>,
 }
 
+fn first_rank() -> usize {
+    1
+}
+
+/// The handicraft with the given name, if there is one
+pub fn find_handicraft<'a>(
+    handicrafts: &'a [Handicraft],
+    name: &HandicraftName,
+) -> Option<&'a Handicraft> {
+    handicrafts
+        .iter()
+        .find(|handicraft| handicraft.name == *name)
+}
+
+/// Like `find_handicraft`, for a step of an agenda made from the same handicrafts, which can't
+/// name one that isn't there
+pub fn agenda_step<'a>(handicrafts: &'a [Handicraft], name: &HandicraftName) -> &'a Handicraft {
+    find_handicraft(handicrafts, name)
+        .unwrap_or_else(|| panic!("Agenda had unknown handicraft ({})", name))
+}
+
 impl Handicraft {
+    /// Whether making `next` straight after this gets the efficiency bonus
+    pub fn gives_bonus_to(&self, next: &Handicraft) -> bool {
+        self.name != next.name
+            && self
+                .category
+                .iter()
+                .any(|category| next.category.contains(category))
+    }
+
     pub fn as_pricing_info(&self) -> HandicraftPricingInfo {
         HandicraftPricingInfo {
             time: self.time,
@@ -191,7 +82,14 @@ pub struct HandicraftPricingInfo {
     pub value: usize,
 }
 
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+impl HandicraftPricingInfo {
+    /// units made by one craft, which the efficiency bonus doubles
+    pub fn units(&self, efficiency_bonus: bool) -> usize {
+        self.quantity * if efficiency_bonus { 2 } else { 1 }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub enum Popularity {
     Low,
     Average,
@@ -229,7 +127,7 @@ impl FromStr for Popularity {
     }
 }
 
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+#[derive(Deserialize, Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub enum Supply {
     Nonexistent,
     Insufficient,
@@ -248,6 +146,17 @@ impl Supply {
             Supply::Overflowing => 0.6,
         }
     }
+
+    /// the supply after it has risen by the given number of levels, stopping at Overflowing
+    pub fn raised(&self, levels: usize) -> Supply {
+        use Supply::*;
+        let order = [Nonexistent, Insufficient, Sufficient, Surplus, Overflowing];
+        let current = order
+            .iter()
+            .position(|supply| supply == self)
+            .expect("Supply missing from order");
+        order[(current + levels).min(order.len() - 1)]
+    }
 }
 
 #[derive(Error, Debug)]
@@ -270,12 +179,137 @@ impl FromStr for Supply {
     }
 }
 
+/// The arrows shown next to a handicraft's supply, predicting where demand goes next
+#[derive(Deserialize, Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum DemandShift {
+    Skyrocketing,
+    Increasing,
+    Steady,
+    Decreasing,
+    Plummeting,
+}
+
+#[derive(Error, Debug)]
+#[error("Invalid value for DemandShift: {0}")]
+pub struct DemandShiftDeserializeError(String);
+
+impl FromStr for DemandShift {
+    type Err = DemandShiftDeserializeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use DemandShift::*;
+        match s.to_lowercase().as_str() {
+            "s" | "++" => Ok(Skyrocketing),
+            "i" | "+" => Ok(Increasing),
+            "n" | "=" => Ok(Steady),
+            "d" | "-" => Ok(Decreasing),
+            "p" | "--" => Ok(Plummeting),
+            _ => Err(DemandShiftDeserializeError(s.to_string())),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[serde(try_from = "usize")]
+pub enum WorkshopRank {
+    #[default]
+    One,
+    Two,
+    Three,
+    Four,
+    Five,
+}
+
+impl WorkshopRank {
+    pub fn multiplier(&self) -> f64 {
+        match self {
+            WorkshopRank::One => 1.0,
+            WorkshopRank::Two => 1.1,
+            WorkshopRank::Three => 1.2,
+            WorkshopRank::Four => 1.3,
+            WorkshopRank::Five => 1.4,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+#[error("Invalid value for WorkshopRank: {0}")]
+pub struct WorkshopRankDeserializeError(String);
+
+impl FromStr for WorkshopRank {
+    type Err = WorkshopRankDeserializeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use WorkshopRank::*;
+        match s.trim() {
+            "1" => Ok(One),
+            "2" => Ok(Two),
+            "3" => Ok(Three),
+            "4" => Ok(Four),
+            "5" => Ok(Five),
+            _ => Err(WorkshopRankDeserializeError(s.to_string())),
+        }
+    }
+}
+
+impl TryFrom<usize> for WorkshopRank {
+    type Error = WorkshopRankDeserializeError;
+
+    fn try_from(rank: usize) -> Result<Self, Self::Error> {
+        rank.to_string().parse()
+    }
+}
+
+/// Workshop-wide modifiers applied to the base value of every handicraft
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Default)]
+pub struct PricingModifiers {
+    pub workshop_rank: WorkshopRank,
+    /// current groove, as a percentage bonus
+    pub groove: usize,
+}
+
+/// Groove shared by all workshops, which rises by one for every efficiency bonus craft until it
+/// reaches the cap
+#[derive(Serialize, Debug, Clone, Copy, Hash, PartialEq, Eq, Default)]
+pub struct Groove {
+    pub value: usize,
+    pub cap: usize,
+}
+
+impl Groove {
+    pub fn new(value: usize, cap: usize) -> Self {
+        Groove {
+            value: value.min(cap),
+            cap,
+        }
+    }
+
+    pub fn increment(&mut self) {
+        self.value = (self.value + 1).min(self.cap);
+    }
+}
+
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub struct PopSupply {
     pub popularity: Popularity,
     pub supply: Supply,
 }
 
+/// What the workshop showed for a handicraft on one day of the season
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct DemandObservation {
+    pub pop_supply: PopSupply,
+    pub demand_shift: DemandShift,
+}
+
+/// A material recipes can use. Rare materials run short, and are budgeted through the rare table
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Material {
+    pub name: MaterialName,
+    #[serde(default)]
+    pub rare: bool,
+}
+
 #[derive(Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct RareItem {
     pub name: MaterialName,
@@ -294,28 +328,172 @@ pub struct RareItems {
     pub leavings: Vec<RareItem>,
 }
 
+/// The weekly popularity tables, which come up in a fixed rotation
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct PopularityTables {
+    /// start of the season counted as week 0, for reference
+    pub reference_date: Option<String>,
+    /// table ids in the order they come up, starting with week 0
+    pub rotation: Vec<String>,
+    pub tables: HashMap<String, HashMap<HandicraftName, Popularity>>,
+}
+
+#[derive(Debug, Error)]
+pub enum PopularityTableError {
+    #[error("No popularity table with id {0}")]
+    UnknownTable(String),
+    #[error("Popularity table rotation is empty; give popularity in the input, or use a data file whose [popularity] section fills in the rotation and tables")]
+    EmptyRotation,
+    #[error("The popularity tables have no reference date to count weeks from")]
+    NoReferenceDate,
+    #[error("Expected a date like 2022-08-23, got {0}")]
+    BadDate(String),
+    #[error("{date} is before the reference date {reference}")]
+    BeforeReference { date: String, reference: String },
+}
+
+// days since 1970-01-01 of a YYYY-MM-DD date, in the proleptic Gregorian calendar
+pub(crate) fn day_number(date: &str) -> Option<i64> {
+    let mut parts = date.trim().splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: i64 = parts.next()?.parse().ok()?;
+    let day: i64 = parts.next()?.parse().ok()?;
+    let days_in_month = match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return None,
+    };
+    if !(1..=days_in_month).contains(&day) {
+        return None;
+    }
+    // counts years from March so the leap day falls at the end
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    Some(era * 146097 + day_of_era - 719468)
+}
+
+impl PopularityTables {
+    pub fn table(
+        &self,
+        id: &str,
+    ) -> Result<HashMap<HandicraftName, Popularity>, PopularityTableError> {
+        self.tables
+            .get(id)
+            .cloned()
+            .ok_or_else(|| PopularityTableError::UnknownTable(id.to_string()))
+    }
+
+    /// The table for a week, counting from the reference date
+    pub fn table_for_week(
+        &self,
+        week: usize,
+    ) -> Result<HashMap<HandicraftName, Popularity>, PopularityTableError> {
+        if self.rotation.is_empty() {
+            return Err(PopularityTableError::EmptyRotation);
+        }
+        self.table(&self.rotation[week % self.rotation.len()])
+    }
+
+    /// The week a date falls in, counting from the reference date
+    pub fn week_for_date(&self, date: &str) -> Result<usize, PopularityTableError> {
+        let reference = self
+            .reference_date
+            .as_deref()
+            .ok_or(PopularityTableError::NoReferenceDate)?;
+        let start =
+            day_number(reference).ok_or_else(|| PopularityTableError::BadDate(reference.into()))?;
+        let day = day_number(date).ok_or_else(|| PopularityTableError::BadDate(date.into()))?;
+        if day < start {
+            return Err(PopularityTableError::BeforeReference {
+                date: date.to_string(),
+                reference: reference.to_string(),
+            });
+        }
+        Ok(((day - start) / 7) as usize)
+    }
+}
+
+/// Figures for how supply moves, which the game doesn't show so are estimates to be tuned
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SupplyModel {
+    /// units of a handicraft made before its supply rises by a level
+    #[serde(default = "units_per_supply_level")]
+    pub units_per_level: usize,
+}
+
+fn units_per_supply_level() -> usize {
+    8
+}
+
+impl Default for SupplyModel {
+    fn default() -> Self {
+        SupplyModel {
+            units_per_level: units_per_supply_level(),
+        }
+    }
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct WorkshopData {
     pub handicrafts: Vec<Handicraft>,
+    #[serde(default)]
+    pub materials: Vec<Material>,
     pub rare: RareItems,
+    #[serde(default)]
+    pub popularity: PopularityTables,
+    #[serde(default)]
+    pub supply: SupplyModel,
 }
 
 #[derive(Debug, Error)]
 pub enum WorkshopDataReadError {
-    #[error("Could not parse handicrafts data as toml")]
-    Toml,
-    #[error("Malformed handicrafts data")]
-    Malformed,
+    #[error("Could not parse handicrafts data as toml: {0}")]
+    Toml(toml::de::Error),
+    #[error("Malformed handicrafts data: {0}")]
+    Malformed(toml::de::Error),
+    #[error(transparent)]
+    Invalid(#[from] DataValidationErrors),
+    #[error("Could not read workshop data from {path}: {source}")]
+    Io { path: PathBuf, source: io::Error },
+    #[error("Could not apply overlay {path}: {reason}")]
+    Overlay { path: PathBuf, reason: String },
 }
 
 impl WorkshopData {
+    pub fn rare_items(&self) -> impl Iterator<Item = RareItemVariant> + '_ {
+        self.rare
+            .material
+            .iter()
+            .cloned()
+            .map(RareItemVariant::WithArea)
+            .chain(
+                self.rare
+                    .produce
+                    .iter()
+                    .cloned()
+                    .map(RareItemVariant::RareItem),
+            )
+            .chain(
+                self.rare
+                    .leavings
+                    .iter()
+                    .cloned()
+                    .map(RareItemVariant::RareItem),
+            )
+    }
+
+    /// The copy of the data built into the binary
     pub fn try_default() -> Result<Self, WorkshopDataReadError> {
-        let raw = include_bytes!("handicrafts.toml");
-        String::from_utf8_lossy(raw)
-            .parse::<toml::Value>()
-            .map_err(|_| WorkshopDataReadError::Toml)?
-            .try_into()
-            .map_err(|_| WorkshopDataReadError::Malformed)
+        let data = crate::data::embedded()?;
+        let data: Self =
+            crate::name::interning(|| data.try_into()).map_err(WorkshopDataReadError::Malformed)?;
+        data.validate()?;
+        Ok(data)
     }
 }
 
@@ -346,21 +524,238 @@ impl RareItemCount {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// How many of each material that can run out is on hand. Materials left out never run short
+pub type MaterialBudget = HashMap<MaterialName, usize>;
+
+/// Budget for the rare items on hand, along with the rest of the materials in an inventory
+pub fn material_budget(
+    rare_item_counts: &[RareItemCount],
+    inventory: &HashMap<MaterialName, usize>,
+) -> MaterialBudget {
+    inventory
+        .iter()
+        .map(|(name, count)| (*name, *count))
+        .chain(
+            rare_item_counts
+                .iter()
+                .map(|item| (*item.name(), item.count)),
+        )
+        .collect()
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
 pub struct Agenda {
     pub handicrafts: Vec<HandicraftName>,
     pub values: Vec<usize>,
     pub total_value: usize,
 }
 
+/// How much of a material a plan uses, against how many were in the budget if it can run out
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaterialUse {
+    pub material: MaterialName,
+    pub used: usize,
+    pub on_hand: Option<usize>,
+}
+
+impl MaterialUse {
+    pub fn shortfall(&self) -> usize {
+        self.on_hand
+            .map_or(0, |on_hand| self.used.saturating_sub(on_hand))
+    }
+}
+
+/// Agendas for every workshop over one cycle, priced with groove carried between crafts
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct GrooveAgendas {
+    pub agendas: Vec<Agenda>,
+    pub total_value: usize,
+    /// groove left once every workshop has finished, for starting the next cycle
+    pub groove: Groove,
+}
+
+/// How far supply drops on a peak, ordered from the smaller drop
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PeakStrength {
+    Weak,
+    Strong,
+}
+
+/// The day of the season (2-7) on which a handicraft's supply bottoms out
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct Peak {
+    pub day: usize,
+    pub strength: PeakStrength,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub enum DayPlan {
+    Rest,
+    Work(GrooveAgendas),
+}
+
+impl DayPlan {
+    pub fn total_value(&self) -> usize {
+        match self {
+            DayPlan::Rest => 0,
+            DayPlan::Work(agendas) => agendas.total_value,
+        }
+    }
+}
+
+/// Plans for every day of a season, starting from Day 1
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct WeekPlan {
+    pub days: Vec<DayPlan>,
+    pub total_value: usize,
+    /// groove left at the end of the season
+    pub groove: Groove,
+}
+
+impl WeekPlan {
+    /// Every agenda worked over the season, day by day
+    pub fn agendas(&self) -> impl Iterator<Item = &Agenda> {
+        self.days.iter().flat_map(|day| match day {
+            DayPlan::Rest => [].iter(),
+            DayPlan::Work(agendas) => agendas.agendas.iter(),
+        })
+    }
+}
+
+impl Agenda {
+    /// Whether each step gets the efficiency bonus, from sharing a category with the step before
+    /// it without being the same handicraft
+    pub fn efficiency_bonuses(&self, handicrafts: &[Handicraft]) -> Vec<bool> {
+        let find = |name| agenda_step(handicrafts, name);
+        iter::once(false)
+            .chain(
+                self.handicrafts
+                    .windows(2)
+                    .map(|step| find(&step[0]).gives_bonus_to(find(&step[1]))),
+            )
+            .take(self.handicrafts.len())
+            .collect()
+    }
+}
+
 impl PartialOrd for Agenda {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         Some(self.cmp(other))
     }
 }
 
+// agendas worth the same are ranked by their handicrafts so that ties always come out in the same
+// order, with the earlier sequence ranking higher
 impl Ord for Agenda {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.total_value.cmp(&other.total_value)
+        self.total_value
+            .cmp(&other.total_value)
+            .then_with(|| other.handicrafts.cmp(&self.handicrafts))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, handicraft};
+    use crate::validate::DataValidationError;
+
+    const TABLES: &str = r#"
+        reference_date = "2022-08-23"
+        rotation = ["2", "1", "3"]
+
+        [tables.1]
+        IsleworksPotion = "High"
+        IsleworksFiresand = "Low"
+
+        [tables.2]
+        IsleworksPotion = "VeryHigh"
+        IsleworksFiresand = "Average"
+    "#;
+
+    #[test]
+    fn bundled_popularity_rotation_gives_a_table_for_every_week() {
+        let data = testing::data();
+        let rotation = &data.popularity.rotation;
+        for week in 0..rotation.len() {
+            let table = data.popularity.table_for_week(week).unwrap();
+            for handicraft in &data.handicrafts {
+                assert!(
+                    table.contains_key(&handicraft.name),
+                    "week {} has no popularity for {}",
+                    week,
+                    handicraft.name
+                );
+            }
+        }
+        if rotation.is_empty() {
+            assert!(matches!(
+                data.popularity.table_for_week(0),
+                Err(PopularityTableError::EmptyRotation)
+            ));
+        }
+    }
+
+    #[test]
+    fn popularity_tables_come_up_by_week_and_date() {
+        let data = testing::data();
+        let tables: PopularityTables = TABLES.parse::<toml::Value>().unwrap().try_into().unwrap();
+        let potion = handicraft("IsleworksPotion");
+
+        assert_eq!(tables.table("1").unwrap()[&potion], Popularity::High);
+        assert!(matches!(
+            tables.table("4"),
+            Err(PopularityTableError::UnknownTable(id)) if id == "4"
+        ));
+        assert_eq!(
+            tables.table_for_week(0).unwrap()[&potion],
+            Popularity::VeryHigh
+        );
+        assert_eq!(tables.table_for_week(4).unwrap()[&potion], Popularity::High);
+        assert!(matches!(
+            tables.table_for_week(2),
+            Err(PopularityTableError::UnknownTable(_))
+        ));
+        assert!(matches!(
+            PopularityTables::default().table_for_week(0),
+            Err(PopularityTableError::EmptyRotation)
+        ));
+
+        for (date, week) in [
+            ("2022-08-23", 0),
+            ("2022-08-29", 0),
+            ("2022-08-30", 1),
+            ("2023-08-22", 52),
+            ("2024-03-05", 80),
+        ] {
+            assert_eq!(tables.week_for_date(date).unwrap(), week, "{}", date);
+        }
+        assert!(matches!(
+            tables.week_for_date("2022-08-22"),
+            Err(PopularityTableError::BeforeReference { .. })
+        ));
+        assert!(matches!(
+            tables.week_for_date("2023-02-29"),
+            Err(PopularityTableError::BadDate(_))
+        ));
+
+        let data = WorkshopData {
+            popularity: tables,
+            ..data.clone()
+        };
+        let errors = data.validate().unwrap_err().0;
+        assert!(
+            errors.contains(&DataValidationError::UnknownPopularityTable(
+                "3".to_string()
+            ))
+        );
+        assert!(errors.contains(&DataValidationError::MissingPopularity {
+            table: "1".to_string(),
+            handicraft: "IsleworksWoodenChair".parse().unwrap(),
+        }));
+        assert!(!errors.iter().any(|error| matches!(
+            error,
+            DataValidationError::MissingPopularity { handicraft, .. } if *handicraft == potion
+        )));
     }
 }
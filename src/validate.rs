@@ -0,0 +1,379 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use thiserror::Error;
+
+use crate::agenda::TIME_IN_CYCLE;
+use crate::share::CODE_LIMIT;
+use crate::types::{day_number, HandicraftName, MaterialName, WorkshopData};
+
+/// A problem with one entry in the workshop data
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum DataValidationError {
+    #[error("{0} is listed more than once")]
+    DuplicateHandicraft(HandicraftName),
+    #[error("{0} takes no time to make")]
+    NonPositiveTime(HandicraftName),
+    #[error("{handicraft} takes {time} hours, which is not a multiple of 2")]
+    OddTime {
+        handicraft: HandicraftName,
+        time: usize,
+    },
+    #[error(
+        "{handicraft} takes {time} hours, which is longer than a cycle ({TIME_IN_CYCLE} hours)"
+    )]
+    TimeTooLong {
+        handicraft: HandicraftName,
+        time: usize,
+    },
+    #[error("{handicraft} has share code {code}, which {other} already has")]
+    DuplicateCode {
+        handicraft: HandicraftName,
+        other: HandicraftName,
+        code: usize,
+    },
+    #[error("{handicraft} has share code {code}, which is not below {CODE_LIMIT}")]
+    CodeTooLarge {
+        handicraft: HandicraftName,
+        code: usize,
+    },
+    #[error("{0} has no categories")]
+    NoCategory(HandicraftName),
+    #[error("{handicraft} uses {material}, which is not in the materials list")]
+    UnknownMaterial {
+        handicraft: HandicraftName,
+        material: MaterialName,
+    },
+    #[error("Rare item {0} is not in the materials list")]
+    UnknownRareItem(MaterialName),
+    #[error("Rare item {0} is not used by any handicraft")]
+    UnusedRareItem(MaterialName),
+    #[error("{handicraft} uses {material}, which is rare but missing from the rare table")]
+    MissingRareItem {
+        handicraft: HandicraftName,
+        material: MaterialName,
+    },
+    #[error("Popularity reference date {0} is not a date like 2022-08-23")]
+    BadReferenceDate(String),
+    #[error("Popularity rotation names table {0}, which doesn't exist")]
+    UnknownPopularityTable(String),
+    #[error("Popularity table {table} is missing {handicraft}")]
+    MissingPopularity {
+        table: String,
+        handicraft: HandicraftName,
+    },
+    #[error("Popularity table {table} lists {handicraft}, which is not a handicraft")]
+    UnknownPopularityHandicraft {
+        table: String,
+        handicraft: HandicraftName,
+    },
+    #[error("Supply units_per_level is 0, so supply would never rise")]
+    NoUnitsPerSupplyLevel,
+}
+
+/// Every problem found in the workshop data
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub struct DataValidationErrors(pub Vec<DataValidationError>);
+
+impl fmt::Display for DataValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid workshop data:")?;
+        for error in &self.0 {
+            write!(f, "\n  {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl WorkshopData {
+    /// Checks every entry, collecting all the problems rather than stopping at the first
+    pub fn validate(&self) -> Result<(), DataValidationErrors> {
+        use DataValidationError::*;
+
+        let mut errors = Vec::new();
+        // the materials list is optional, and recipes are only checked against it when given
+        let materials: Option<HashMap<_, _>> = (!self.materials.is_empty()).then(|| {
+            self.materials
+                .iter()
+                .map(|material| (material.name, material.rare))
+                .collect()
+        });
+        let rare_items: HashSet<_> = self.rare_items().map(|rare| *rare.name()).collect();
+
+        let mut seen = HashSet::new();
+        let mut reported = HashSet::new();
+        let mut used = HashSet::new();
+        let mut codes = HashMap::new();
+        for handicraft in &self.handicrafts {
+            let name = handicraft.name;
+            if !seen.insert(name) && reported.insert(name) {
+                errors.push(DuplicateHandicraft(name));
+            }
+
+            let code = handicraft.code;
+            if code >= CODE_LIMIT {
+                errors.push(CodeTooLarge {
+                    handicraft: name,
+                    code,
+                });
+            } else if let Some(&other) = codes.get(&code) {
+                // a repeated entry already has its own error
+                if other != name {
+                    errors.push(DuplicateCode {
+                        handicraft: name,
+                        other,
+                        code,
+                    });
+                }
+            } else {
+                codes.insert(code, name);
+            }
+
+            let time = handicraft.time;
+            if time == 0 {
+                errors.push(NonPositiveTime(name));
+            } else if time % 2 != 0 {
+                errors.push(OddTime {
+                    handicraft: name,
+                    time,
+                });
+            }
+            if time > TIME_IN_CYCLE {
+                errors.push(TimeTooLong {
+                    handicraft: name,
+                    time,
+                });
+            }
+
+            if handicraft.category.is_empty() {
+                errors.push(NoCategory(name));
+            }
+
+            let mut recipe: Vec<_> = handicraft.materials.keys().copied().collect();
+            recipe.sort();
+            for material in recipe {
+                used.insert(material);
+                let Some(materials) = &materials else {
+                    continue;
+                };
+                match materials.get(&material) {
+                    None => errors.push(UnknownMaterial {
+                        handicraft: name,
+                        material,
+                    }),
+                    Some(true) if !rare_items.contains(&material) => errors.push(MissingRareItem {
+                        handicraft: name,
+                        material,
+                    }),
+                    Some(_) => {}
+                }
+            }
+        }
+
+        for rare in self.rare_items() {
+            let name = *rare.name();
+            if materials
+                .as_ref()
+                .is_some_and(|materials| !materials.contains_key(&name))
+            {
+                errors.push(UnknownRareItem(name));
+            }
+            if !used.contains(&name) {
+                errors.push(UnusedRareItem(name));
+            }
+        }
+
+        let popularity = &self.popularity;
+        if let Some(date) = &popularity.reference_date {
+            if day_number(date).is_none() {
+                errors.push(BadReferenceDate(date.clone()));
+            }
+        }
+        let mut reported = HashSet::new();
+        for id in &popularity.rotation {
+            if !popularity.tables.contains_key(id) && reported.insert(id) {
+                errors.push(UnknownPopularityTable(id.clone()));
+            }
+        }
+        let mut tables: Vec<_> = popularity.tables.iter().collect();
+        tables.sort_by_key(|(id, _)| *id);
+        for (id, table) in tables {
+            for handicraft in &self.handicrafts {
+                if !table.contains_key(&handicraft.name) {
+                    errors.push(MissingPopularity {
+                        table: id.clone(),
+                        handicraft: handicraft.name,
+                    });
+                }
+            }
+            let mut listed: Vec<_> = table.keys().copied().collect();
+            listed.sort();
+            for handicraft in listed {
+                if !seen.contains(&handicraft) {
+                    errors.push(UnknownPopularityHandicraft {
+                        table: id.clone(),
+                        handicraft,
+                    });
+                }
+            }
+        }
+
+        if self.supply.units_per_level == 0 {
+            errors.push(NoUnitsPerSupplyLevel);
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(DataValidationErrors(errors))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{handicraft, material};
+
+    const BROKEN: &str = r#"
+[[handicrafts]]
+name = "TestPotion"
+code = 1
+time = 4
+quantity = 1
+value = 10
+category = ["TestConcoctions"]
+materials = { TestSap = 1, TestMilk = 1 }
+
+[[handicrafts]]
+name = "TestPotion"
+code = 1
+time = 3
+quantity = 1
+value = 10
+category = []
+materials = { TestOre = 2 }
+
+[[handicrafts]]
+name = "TestBoulder"
+code = 1
+time = 0
+quantity = 1
+value = 10
+category = ["TestConcoctions"]
+materials = {}
+
+[[handicrafts]]
+name = "TestStatue"
+code = 1296
+time = 26
+quantity = 1
+value = 10
+category = ["TestConcoctions"]
+materials = { TestGem = 1 }
+
+[[materials]]
+name = "TestSap"
+
+[[materials]]
+name = "TestMilk"
+rare = true
+
+[[materials]]
+name = "TestGem"
+rare = true
+
+[rare]
+produce = []
+material = [{ name = "TestShell", area = "TestBeach" }]
+leavings = [{ name = "TestMilk" }]
+
+[popularity]
+reference_date = "2022-13-01"
+rotation = ["a", "b"]
+
+[popularity.tables.a]
+TestPotion = "High"
+TestGhost = "Low"
+
+[supply]
+units_per_level = 0
+"#;
+
+    #[test]
+    fn validation_reports_every_problem() {
+        let mut data: WorkshopData =
+            crate::name::interning(|| BROKEN.parse::<toml::Value>().unwrap().try_into().unwrap());
+        let (potion, boulder, statue) = (
+            handicraft("TestPotion"),
+            handicraft("TestBoulder"),
+            handicraft("TestStatue"),
+        );
+        use DataValidationError::*;
+
+        let unknown_ore = UnknownMaterial {
+            handicraft: potion,
+            material: material("TestOre"),
+        };
+        let missing_gem = MissingRareItem {
+            handicraft: statue,
+            material: material("TestGem"),
+        };
+        let unknown_shell = UnknownRareItem(material("TestShell"));
+        let errors = vec![
+            DuplicateHandicraft(potion),
+            OddTime {
+                handicraft: potion,
+                time: 3,
+            },
+            NoCategory(potion),
+            unknown_ore.clone(),
+            DuplicateCode {
+                handicraft: boulder,
+                other: potion,
+                code: 1,
+            },
+            NonPositiveTime(boulder),
+            CodeTooLarge {
+                handicraft: statue,
+                code: 1296,
+            },
+            TimeTooLong {
+                handicraft: statue,
+                time: 26,
+            },
+            missing_gem.clone(),
+            unknown_shell.clone(),
+            UnusedRareItem(material("TestShell")),
+            BadReferenceDate("2022-13-01".to_string()),
+            UnknownPopularityTable("b".to_string()),
+            MissingPopularity {
+                table: "a".to_string(),
+                handicraft: boulder,
+            },
+            MissingPopularity {
+                table: "a".to_string(),
+                handicraft: statue,
+            },
+            UnknownPopularityHandicraft {
+                table: "a".to_string(),
+                handicraft: handicraft("TestGhost"),
+            },
+            NoUnitsPerSupplyLevel,
+        ];
+        assert_eq!(data.validate(), Err(DataValidationErrors(errors.clone())));
+
+        // without a materials list, recipes and rare items aren't checked against it
+        data.materials.clear();
+        let without_materials: Vec<_> = errors
+            .into_iter()
+            .filter(|error| ![&unknown_ore, &missing_gem, &unknown_shell].contains(&error))
+            .collect();
+        assert_eq!(
+            data.validate(),
+            Err(DataValidationErrors(without_materials))
+        );
+    }
+}